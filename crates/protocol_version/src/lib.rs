@@ -154,7 +154,8 @@ const V7: ProtocolVersion = ProtocolVersion {
 /// Corresponds to server's execution_version 8 (protocol v32.0, zksync-os 0.4.0 native batch prover)
 const V8: ProtocolVersion = ProtocolVersion {
     // Keccak256 of the phase-3 SNARK VK (`generate-vk --check-aux-params`), so it binds the
-    // app binary below. Regenerate when the binary, the level, or the pins change.
+    // app binary below. Regenerate when the binary, the level, or the pins change;
+    // `zksync_os_snark_prover generate-vk` reproduces and checks it.
     vk_hash: VerificationKeyHash(
         "0x9f7576b911e7d3f528d49f894208682c81800814db9e3beac7fc3b1c4d626e7a",
     ),
@@ -0,0 +1,144 @@
+//! Binary payloads of the prover API: length-prefixed frames, optionally compressed.
+//!
+//! A binary body is a sequence of frames, each a big-endian `u32` length followed by that
+//! many bytes. The first frame is the JSON payload without its base64 fields; those follow
+//! as one raw frame each (a list field, such as a SNARK job's `fri_proofs`, one frame per
+//! element). The mock converts between both forms through the JSON payloads.
+
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, bail, Context as _};
+use axum::http::{
+    header::{ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE},
+    HeaderMap,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest as _, Sha256};
+
+pub(crate) const CONTENT_TYPE_BINARY: &str = "application/octet-stream";
+pub(crate) const CONTENT_DIGEST: &str = "content-digest";
+
+fn header(headers: &HeaderMap, name: axum::http::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Whether the request body is binary.
+pub(crate) fn is_binary(headers: &HeaderMap) -> bool {
+    header(headers, CONTENT_TYPE).is_some_and(|value| value.starts_with(CONTENT_TYPE_BINARY))
+}
+
+/// Whether the client accepts binary responses.
+pub(crate) fn accepts_binary(headers: &HeaderMap) -> bool {
+    header(headers, ACCEPT).is_some_and(|value| value.contains(CONTENT_TYPE_BINARY))
+}
+
+/// The first encoding of `Accept-Encoding` the mock can produce, if any.
+pub(crate) fn response_encoding(headers: &HeaderMap) -> Option<&'static str> {
+    header(headers, ACCEPT_ENCODING)?
+        .split(',')
+        .map(|token| token.split(';').next().unwrap_or_default().trim())
+        .find_map(|token| match token {
+            "zstd" => Some("zstd"),
+            "gzip" => Some("gzip"),
+            _ => None,
+        })
+}
+
+pub(crate) fn compress(encoding: &str, body: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    match encoding {
+        "gzip" => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&body)?;
+            Ok(encoder.finish()?)
+        }
+        "zstd" => Ok(zstd::encode_all(body.as_slice(), 0)?),
+        other => bail!("unsupported content encoding {other:?}"),
+    }
+}
+
+/// `Content-Digest` (RFC 9530) of a body as sent: `sha-256=:<base64>:`.
+pub(crate) fn content_digest(body: &[u8]) -> String {
+    format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// Check a request body against its `Content-Digest`, if it carries one.
+pub(crate) fn verify_digest(headers: &HeaderMap, body: &[u8]) -> anyhow::Result<()> {
+    let Some(announced) = header(headers, axum::http::HeaderName::from_static(CONTENT_DIGEST))
+    else {
+        return Ok(());
+    };
+    let actual = content_digest(body);
+    if announced.split(',').any(|member| member.trim() == actual) {
+        Ok(())
+    } else {
+        bail!("content digest mismatch: announced {announced}, body has {actual}")
+    }
+}
+
+/// Undo the request's `Content-Encoding`, if any.
+pub(crate) fn decompress(headers: &HeaderMap, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    match header(headers, CONTENT_ENCODING) {
+        None | Some("identity") => Ok(body.to_vec()),
+        Some("gzip") => {
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(body).read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }
+        Some("zstd") => Ok(zstd::decode_all(body)?),
+        Some(other) => bail!("unsupported content encoding {other:?}"),
+    }
+}
+
+/// Frame `payload` with its base64 `field` (a string or a list of strings) moved out of
+/// the JSON header into raw frames.
+pub(crate) fn encode<P: Serialize>(payload: &P, field: &str) -> anyhow::Result<Vec<u8>> {
+    let Value::Object(mut header) = serde_json::to_value(payload)? else {
+        bail!("payloads are JSON objects");
+    };
+    let blobs = match header.remove(field) {
+        Some(Value::String(blob)) => vec![STANDARD.decode(blob)?],
+        Some(Value::Array(blobs)) => blobs
+            .iter()
+            .map(|blob| Ok(STANDARD.decode(blob.as_str().context("blobs are strings")?)?))
+            .collect::<anyhow::Result<_>>()?,
+        _ => bail!("payload has no {field:?} field"),
+    };
+
+    let mut body = Vec::new();
+    for frame in std::iter::once(serde_json::to_vec(&header)?).chain(blobs) {
+        body.extend_from_slice(&u32::try_from(frame.len())?.to_be_bytes());
+        body.extend_from_slice(&frame);
+    }
+    Ok(body)
+}
+
+/// Inverse of [`encode`] for a payload with a single base64 `field`.
+pub(crate) fn decode<P: DeserializeOwned>(body: &[u8], field: &str) -> anyhow::Result<P> {
+    let mut frames = vec![];
+    let mut rest = body;
+    while !rest.is_empty() {
+        let (len, tail) = rest
+            .split_first_chunk::<4>()
+            .context("truncated frame length")?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if tail.len() < len {
+            bail!("frame {} is truncated", frames.len());
+        }
+        let (frame, tail) = tail.split_at(len);
+        frames.push(frame);
+        rest = tail;
+    }
+    let [header, blob] = frames[..] else {
+        return Err(anyhow!(
+            "expected a header and one {field:?} frame, got {} frames",
+            frames.len()
+        ));
+    };
+
+    let mut header: Map<String, Value> = serde_json::from_slice(header)?;
+    header.insert(field.to_string(), STANDARD.encode(blob).into());
+    Ok(serde_json::from_value(header.into())?)
+}
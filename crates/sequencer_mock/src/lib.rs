@@ -0,0 +1,517 @@
+//! In-process mock of the sequencer prover API (`prover-jobs/v1/...`).
+//!
+//! Serves scripted FRI/SNARK jobs, records every request and submission, and can inject
+//! status codes and latency per route, so `SequencerProofClient` (and the provers built on
+//! it) can be driven end to end without a live sequencer.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use zksync_sequencer_mock::{FriJob, MockSequencer, MockSequencerConfig};
+//!
+//! let sequencer = MockSequencer::start(MockSequencerConfig::default()).await?;
+//! sequencer.push_fri_job(FriJob {
+//!     batch_number: 1,
+//!     vk_hash: "0xabcd".to_string(),
+//!     prover_input: vec![0; 16],
+//!     ..Default::default()
+//! });
+//! // point a prover at `sequencer.url()`, then inspect `sequencer.fri_submissions()`
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle};
+use url::Url;
+
+pub use axum::http::StatusCode;
+
+mod binary;
+mod server;
+
+/// A route of the prover API, used to address recorded requests and injected faults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    Info,
+    PickFri,
+    SubmitFri,
+    PickSnark,
+    SubmitSnark,
+    PeekFri,
+    PeekSnark,
+    FailedFri,
+    SubmitFailedFri,
+    HeartbeatFri,
+    HeartbeatSnark,
+    ReleaseFri,
+    ReleaseSnark,
+}
+
+/// A FRI job queued on the mock.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FriJob {
+    pub batch_number: u32,
+    pub vk_hash: String,
+    pub prover_input: Vec<u8>,
+    /// Output hash sent along with the job, if any
+    pub expected_hash_u32s: Option<[u32; 8]>,
+    pub last_block_timestamp: Option<u64>,
+}
+
+/// A SNARK job queued on the mock. `fri_proofs` are bincode-serialized
+/// `UnrolledProgramProof`s, sent base64-encoded like the real sequencer does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnarkJob {
+    pub from_batch_number: u32,
+    pub to_batch_number: u32,
+    pub vk_hash: String,
+    pub fri_proofs: Vec<Vec<u8>>,
+}
+
+/// A FRI proof received on `FRI/submit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriSubmission {
+    pub prover_id: Option<String>,
+    pub batch_number: u64,
+    pub vk_hash: String,
+    /// base64-encoded bincode of the proof, as sent by the client (binary submissions are
+    /// re-encoded)
+    pub proof: String,
+}
+
+/// A SNARK proof received on `SNARK/submit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnarkSubmission {
+    pub prover_id: Option<String>,
+    pub from_batch_number: u64,
+    pub to_batch_number: u64,
+    pub vk_hash: String,
+    /// base64-encoded serialized SNARK proof, as sent by the client (binary submissions are
+    /// re-encoded)
+    pub proof: String,
+}
+
+/// A picked job handed back on `FRI/{batch}/release` or `SNARK/{from}/{to}/release`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRelease {
+    pub prover_id: Option<String>,
+    pub job: ReleasedJob,
+    /// Reason code as sent by the client, e.g. `unsupported_version`
+    pub reason: String,
+    pub message: String,
+}
+
+/// The job a [`JobRelease`] is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleasedJob {
+    Fri {
+        batch_number: u32,
+    },
+    Snark {
+        from_batch_number: u32,
+        to_batch_number: u32,
+    },
+}
+
+/// A request as seen by the mock, recorded before auth and fault handling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub route: Route,
+    /// `id` query parameter (prover name), if sent
+    pub prover_id: Option<String>,
+    /// `supported_vk_hashes` query parameter, if sent
+    pub supported_vk_hashes: Option<Vec<String>>,
+    /// `wait_ms` query parameter (long-poll wait), if sent
+    pub wait_ms: Option<u64>,
+    /// `Content-Type` header, if sent
+    pub content_type: Option<String>,
+    /// `Content-Encoding` header, if sent
+    pub content_encoding: Option<String>,
+    /// `Accept-Encoding` header, if sent
+    pub accept_encoding: Option<String>,
+    /// `Authorization` header, if sent
+    pub authorization: Option<String>,
+    /// `Idempotency-Key` header, if sent
+    pub idempotency_key: Option<String>,
+    /// `traceparent` header (W3C trace context), if sent
+    pub traceparent: Option<String>,
+    /// Whether the request passed the Basic auth check (always `true` without credentials)
+    pub authorized: bool,
+}
+
+/// A fault injected into the next request of a route.
+#[derive(Debug, Clone, Default)]
+pub struct Fault {
+    /// Status to answer with instead of handling the request; `None` handles it normally.
+    pub status: Option<StatusCode>,
+    /// Delay before answering.
+    pub latency: Duration,
+    /// Damage a job body after its `Content-Digest` is computed, as if it was corrupted in
+    /// transit. Only applies to picks and peeks.
+    pub corrupt_body: bool,
+}
+
+impl Fault {
+    /// Answer with `status` without handling the request.
+    pub fn status(status: StatusCode) -> Self {
+        Self {
+            status: Some(status),
+            ..Self::default()
+        }
+    }
+
+    /// Handle the request normally, but only after `latency`.
+    pub fn latency(latency: Duration) -> Self {
+        Self {
+            latency,
+            ..Self::default()
+        }
+    }
+
+    /// Handle the request normally, but flip a byte of the job body it answers with.
+    pub fn corrupt_body() -> Self {
+        Self {
+            corrupt_body: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Configuration of a [`MockSequencer`].
+#[derive(Debug, Clone, Default)]
+pub struct MockSequencerConfig {
+    /// Address to listen on. `None` binds an ephemeral port on localhost.
+    pub bind_address: Option<SocketAddr>,
+    /// Basic auth `(username, password)` every request must carry. `None` disables the check.
+    pub credentials: Option<(String, String)>,
+}
+
+// Wire formats of the prover API, mirroring the sequencer's JSON payloads.
+
+/// Answer of `GET /prover-jobs/info`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilitiesPayload {
+    pub api_version: String,
+    pub active_vk_hashes: Vec<String>,
+    /// e.g. `heartbeats`, `release`, `long_poll`, `binary_payloads`
+    pub features: Vec<String>,
+    /// Compression accepted on binary request bodies, e.g. `zstd`, `gzip`
+    #[serde(default)]
+    pub content_encodings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriJobPayload {
+    pub batch_number: u32,
+    pub vk_hash: String,
+    pub prover_input: String, // base64-encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_hash_u32s: Option<[u32; 8]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_block_timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitFriProofPayload {
+    pub batch_number: u64,
+    pub vk_hash: String,
+    pub proof: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnarkJobPayload {
+    pub from_batch_number: u64,
+    pub to_batch_number: u64,
+    pub vk_hash: String,
+    pub fri_proofs: Vec<String>, // base64‑encoded FRI proofs
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitSnarkProofPayload {
+    pub from_batch_number: u64,
+    pub to_batch_number: u64,
+    pub vk_hash: String,
+    pub proof: String, // base64‑encoded SNARK proof
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseJobPayload {
+    pub reason: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedFriProofPayload {
+    pub batch_number: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_block_timestamp: Option<u64>,
+    pub expected_hash_u32s: [u32; 8],
+    pub proof_final_register_values: [u32; 16],
+    pub vk_hash: String,
+    pub proof: String, // base64‑encoded FRI proof
+}
+
+impl From<&FriJob> for FriJobPayload {
+    fn from(job: &FriJob) -> Self {
+        Self {
+            batch_number: job.batch_number,
+            vk_hash: job.vk_hash.clone(),
+            prover_input: STANDARD.encode(&job.prover_input),
+            expected_hash_u32s: job.expected_hash_u32s,
+            last_block_timestamp: job.last_block_timestamp,
+        }
+    }
+}
+
+impl TryFrom<FriJobPayload> for FriJob {
+    type Error = anyhow::Error;
+
+    fn try_from(payload: FriJobPayload) -> Result<Self, Self::Error> {
+        Ok(Self {
+            batch_number: payload.batch_number,
+            vk_hash: payload.vk_hash,
+            prover_input: STANDARD
+                .decode(payload.prover_input)
+                .context("prover_input is not valid base64")?,
+            expected_hash_u32s: payload.expected_hash_u32s,
+            last_block_timestamp: payload.last_block_timestamp,
+        })
+    }
+}
+
+impl From<&SnarkJob> for SnarkJobPayload {
+    fn from(job: &SnarkJob) -> Self {
+        Self {
+            from_batch_number: job.from_batch_number as u64,
+            to_batch_number: job.to_batch_number as u64,
+            vk_hash: job.vk_hash.clone(),
+            fri_proofs: job
+                .fri_proofs
+                .iter()
+                .map(|proof| STANDARD.encode(proof))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<SnarkJobPayload> for SnarkJob {
+    type Error = anyhow::Error;
+
+    fn try_from(payload: SnarkJobPayload) -> Result<Self, Self::Error> {
+        Ok(Self {
+            from_batch_number: payload
+                .from_batch_number
+                .try_into()
+                .context("from_batch_number does not fit into u32")?,
+            to_batch_number: payload
+                .to_batch_number
+                .try_into()
+                .context("to_batch_number does not fit into u32")?,
+            vk_hash: payload.vk_hash,
+            fri_proofs: payload
+                .fri_proofs
+                .iter()
+                .enumerate()
+                .map(|(i, proof)| {
+                    STANDARD
+                        .decode(proof)
+                        .with_context(|| format!("fri_proofs[{i}] is not valid base64"))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+/// Mutable state shared between the handle and the server.
+#[derive(Debug, Default)]
+pub(crate) struct MockState {
+    /// Served on the info route; `None` answers `404`, like sequencers predating it.
+    pub(crate) capabilities: Option<CapabilitiesPayload>,
+    pub(crate) fri_jobs: VecDeque<FriJob>,
+    pub(crate) snark_jobs: VecDeque<SnarkJob>,
+    /// Jobs handed out by a pick, kept so they can still be peeked.
+    pub(crate) picked_fri_jobs: BTreeMap<u32, FriJob>,
+    pub(crate) picked_snark_jobs: BTreeMap<(u32, u32), SnarkJob>,
+    pub(crate) failed_fri_proofs: BTreeMap<u32, FailedFriProofPayload>,
+    pub(crate) fri_submissions: Vec<FriSubmission>,
+    pub(crate) snark_submissions: Vec<SnarkSubmission>,
+    /// `Idempotency-Key`s of accepted submissions; a repeated key is answered with `409`.
+    pub(crate) idempotency_keys: HashSet<String>,
+    pub(crate) releases: Vec<JobRelease>,
+    pub(crate) requests: Vec<RecordedRequest>,
+    pub(crate) faults: HashMap<Route, VecDeque<Fault>>,
+    pub(crate) latencies: HashMap<Route, Duration>,
+}
+
+pub(crate) type SharedState = Arc<Mutex<MockState>>;
+
+/// A running mock sequencer. The server shuts down when the handle is dropped.
+pub struct MockSequencer {
+    url: Url,
+    state: SharedState,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl MockSequencer {
+    /// Bind the listener and start serving in a background task.
+    ///
+    /// # Errors
+    /// * if the address cannot be bound
+    pub async fn start(config: MockSequencerConfig) -> anyhow::Result<Self> {
+        let bind_address = config
+            .bind_address
+            .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], 0)));
+        let listener = tokio::net::TcpListener::bind(bind_address)
+            .await
+            .with_context(|| format!("failed to bind mock sequencer to {bind_address}"))?;
+        let local_address = listener.local_addr()?;
+        let url = Url::parse(&format!("http://{local_address}"))?;
+
+        let state = SharedState::default();
+        let router = server::router(state.clone(), config.credentials);
+        let (shutdown, shutdown_receiver) = oneshot::channel();
+        let server = tokio::spawn(async move {
+            let result = axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    shutdown_receiver.await.ok();
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::error!("mock sequencer server failed: {e}");
+            }
+        });
+
+        tracing::info!("Mock sequencer listening on {url}");
+
+        Ok(Self {
+            url,
+            state,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// Base URL of the mock, without credentials.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Base URL of the mock with `username:password@` embedded, as accepted by
+    /// `SequencerEndpoint::parse` and the provers' `--sequencer-urls`.
+    pub fn url_with_credentials(&self, username: &str, password: &str) -> Url {
+        let mut url = self.url.clone();
+        url.set_username(username)
+            .expect("http URLs support credentials");
+        url.set_password(Some(password))
+            .expect("http URLs support credentials");
+        url
+    }
+
+    /// Advertise `capabilities` on `GET /prover-jobs/info`; `None` (the default) makes the
+    /// mock answer `404` there, like a sequencer predating the route.
+    pub fn set_capabilities(&self, capabilities: Option<CapabilitiesPayload>) {
+        self.state().capabilities = capabilities;
+    }
+
+    /// Queue a FRI job; picks hand out queued jobs in FIFO order.
+    pub fn push_fri_job(&self, job: FriJob) {
+        self.state().fri_jobs.push_back(job);
+    }
+
+    /// Queue a SNARK job; picks hand out queued jobs in FIFO order.
+    pub fn push_snark_job(&self, job: SnarkJob) {
+        self.state().snark_jobs.push_back(job);
+    }
+
+    /// Make a failed FRI proof available on `FRI/{batch_number}/failed`.
+    pub fn add_failed_fri_proof(&self, payload: FailedFriProofPayload) {
+        let batch_number = payload.batch_number as u32;
+        self.state().failed_fri_proofs.insert(batch_number, payload);
+    }
+
+    /// Apply `fault` to the next request of `route`. Faults queue up and are consumed in order.
+    pub fn inject_fault(&self, route: Route, fault: Fault) {
+        self.state()
+            .faults
+            .entry(route)
+            .or_default()
+            .push_back(fault);
+    }
+
+    /// Delay every response of `route` by `latency` (on top of any injected fault).
+    pub fn set_latency(&self, route: Route, latency: Duration) {
+        self.state().latencies.insert(route, latency);
+    }
+
+    /// Number of FRI jobs not picked yet.
+    pub fn pending_fri_jobs(&self) -> usize {
+        self.state().fri_jobs.len()
+    }
+
+    /// Number of SNARK jobs not picked yet.
+    pub fn pending_snark_jobs(&self) -> usize {
+        self.state().snark_jobs.len()
+    }
+
+    /// FRI proofs submitted so far, in arrival order.
+    pub fn fri_submissions(&self) -> Vec<FriSubmission> {
+        self.state().fri_submissions.clone()
+    }
+
+    /// SNARK proofs submitted so far, in arrival order.
+    pub fn snark_submissions(&self) -> Vec<SnarkSubmission> {
+        self.state().snark_submissions.clone()
+    }
+
+    /// Jobs released so far, in arrival order.
+    pub fn releases(&self) -> Vec<JobRelease> {
+        self.state().releases.clone()
+    }
+
+    /// Every request received so far, in arrival order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Requests received so far for `route`.
+    pub fn requests_for(&self, route: Route) -> Vec<RecordedRequest> {
+        self.state()
+            .requests
+            .iter()
+            .filter(|request| request.route == route)
+            .cloned()
+            .collect()
+    }
+
+    /// Stop the server and wait for it to finish.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        if let Some(server) = self.server.take() {
+            server.await.ok();
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock sequencer state poisoned")
+    }
+}
+
+impl Drop for MockSequencer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context as _};
+use clap::Parser;
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use zksync_sequencer_mock::{
+    CapabilitiesPayload, FriJob, FriJobPayload, MockSequencer, MockSequencerConfig, SnarkJob,
+    SnarkJobPayload,
+};
+
+/// Standalone mock of the sequencer prover API, serving jobs from files.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Port to listen on
+    #[arg(long, default_value = "3124")]
+    port: u16,
+    /// Require Basic auth with these credentials
+    ///
+    /// Format: username:password
+    #[arg(long, value_name = "USER:PASS")]
+    credentials: Option<String>,
+    /// FRI job to serve, as the sequencer's pick response JSON
+    /// (`{batch_number, vk_hash, prover_input}`, base64 input). Repeat for more jobs.
+    #[arg(long, value_name = "PATH")]
+    fri_job: Vec<PathBuf>,
+    /// SNARK job to serve, as the sequencer's pick response JSON
+    /// (`{from_batch_number, to_batch_number, vk_hash, fri_proofs}`). Repeat for more jobs.
+    #[arg(long, value_name = "PATH")]
+    snark_job: Vec<PathBuf>,
+    /// Capabilities to advertise on `GET /prover-jobs/info`
+    /// (`{api_version, active_vk_hashes, features}`). Answers `404` there if not set.
+    #[arg(long, value_name = "PATH")]
+    capabilities: Option<PathBuf>,
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &PathBuf) -> anyhow::Result<T> {
+    let file = std::fs::File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+    serde_json::from_reader(file).with_context(|| format!("failed to parse {path:?}"))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    FmtSubscriber::builder().with_env_filter(filter).init();
+    let args = Args::parse();
+
+    let credentials = args
+        .credentials
+        .map(|credentials| {
+            credentials
+                .split_once(':')
+                .map(|(username, password)| (username.to_string(), password.to_string()))
+                .ok_or_else(|| anyhow!("--credentials must be in the form username:password"))
+        })
+        .transpose()?;
+
+    let sequencer = MockSequencer::start(MockSequencerConfig {
+        bind_address: Some(SocketAddr::from((Ipv4Addr::UNSPECIFIED, args.port))),
+        credentials,
+    })
+    .await?;
+
+    if let Some(path) = &args.capabilities {
+        sequencer.set_capabilities(Some(read_json::<CapabilitiesPayload>(path)?));
+    }
+    for path in &args.fri_job {
+        let job = FriJob::try_from(read_json::<FriJobPayload>(path)?)
+            .with_context(|| format!("invalid FRI job in {path:?}"))?;
+        tracing::info!(
+            "Serving FRI job for batch {} from {path:?}",
+            job.batch_number
+        );
+        sequencer.push_fri_job(job);
+    }
+    for path in &args.snark_job {
+        let job = SnarkJob::try_from(read_json::<SnarkJobPayload>(path)?)
+            .with_context(|| format!("invalid SNARK job in {path:?}"))?;
+        tracing::info!(
+            "Serving SNARK job for batches [{}, {}] from {path:?}",
+            job.from_batch_number,
+            job.to_batch_number
+        );
+        sequencer.push_snark_job(job);
+    }
+
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Stop request received, shutting down");
+
+    for submission in sequencer.fri_submissions() {
+        tracing::info!(
+            "Received FRI proof for batch {} with vk {} from {:?}",
+            submission.batch_number,
+            submission.vk_hash,
+            submission.prover_id
+        );
+    }
+    for submission in sequencer.snark_submissions() {
+        tracing::info!(
+            "Received SNARK proof for batches [{}, {}] with vk {} from {:?}",
+            submission.from_batch_number,
+            submission.to_batch_number,
+            submission.vk_hash,
+            submission.prover_id
+        );
+    }
+    sequencer.shutdown().await;
+
+    Ok(())
+}
@@ -0,0 +1,608 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{
+        header::{ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE},
+        HeaderMap, HeaderName, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+use crate::{
+    binary, FailedFriProofPayload, Fault, FriJobPayload, FriSubmission, JobRelease, MockState,
+    RecordedRequest, ReleaseJobPayload, ReleasedJob, Route, SharedState, SnarkJobPayload,
+    SnarkSubmission, SubmitFriProofPayload, SubmitSnarkProofPayload,
+};
+
+type Params = Query<HashMap<String, String>>;
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+#[derive(Clone)]
+struct AppState {
+    state: SharedState,
+    /// Full `Authorization` header value requests must carry, if auth is enabled.
+    expected_authorization: Option<String>,
+}
+
+impl AppState {
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock sequencer state poisoned")
+    }
+}
+
+pub(crate) fn router(state: SharedState, credentials: Option<(String, String)>) -> Router {
+    let expected_authorization = credentials.map(|(username, password)| {
+        format!(
+            "Basic {}",
+            STANDARD.encode(format!("{username}:{password}"))
+        )
+    });
+    Router::new()
+        .route("/prover-jobs/info", get(info))
+        .route("/prover-jobs/v1/FRI/pick", post(pick_fri))
+        .route("/prover-jobs/v1/FRI/submit", post(submit_fri))
+        .route("/prover-jobs/v1/FRI/{batch_number}/peek", get(peek_fri))
+        .route(
+            "/prover-jobs/v1/FRI/{batch_number}/failed",
+            get(failed_fri).post(submit_failed_fri),
+        )
+        .route("/prover-jobs/v1/SNARK/pick", post(pick_snark))
+        .route("/prover-jobs/v1/SNARK/submit", post(submit_snark))
+        .route(
+            "/prover-jobs/v1/FRI/{batch_number}/heartbeat",
+            post(heartbeat_fri),
+        )
+        .route(
+            "/prover-jobs/v1/FRI/{batch_number}/release",
+            post(release_fri),
+        )
+        .route(
+            "/prover-jobs/v1/SNARK/{from_batch_number}/{to_batch_number}/peek",
+            get(peek_snark),
+        )
+        .route(
+            "/prover-jobs/v1/SNARK/{from_batch_number}/{to_batch_number}/heartbeat",
+            post(heartbeat_snark),
+        )
+        .route(
+            "/prover-jobs/v1/SNARK/{from_batch_number}/{to_batch_number}/release",
+            post(release_snark),
+        )
+        .with_state(AppState {
+            state,
+            expected_authorization,
+        })
+}
+
+/// The `supported_vk_hashes` query parameter, split; `None` if the client sent none.
+fn supported_vk_hashes(params: &HashMap<String, String>) -> Option<Vec<String>> {
+    params.get("supported_vk_hashes").map(|hashes| {
+        hashes
+            .split(',')
+            .filter(|hash| !hash.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// The `wait_ms` query parameter of a long-polling pick, if the client sent one.
+fn wait_ms(params: &HashMap<String, String>) -> Option<u64> {
+    params.get("wait_ms").and_then(|wait| wait.parse().ok())
+}
+
+/// How often a long-polling pick re-checks the queue.
+const LONG_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Take a job with `take`; if there is none and the client asked to long-poll, keep
+/// checking until one shows up or its `wait_ms` runs out.
+async fn take_job<T>(
+    app: &AppState,
+    params: &HashMap<String, String>,
+    mut take: impl FnMut(&mut MockState) -> Option<T>,
+) -> Option<T> {
+    let deadline =
+        tokio::time::Instant::now() + Duration::from_millis(wait_ms(params).unwrap_or_default());
+    loop {
+        if let Some(job) = take(&mut app.lock()) {
+            return Some(job);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return None;
+        }
+        tokio::time::sleep(LONG_POLL_INTERVAL).await;
+    }
+}
+
+/// Common handling of every route: record the request, apply latency, then check auth and
+/// injected faults. `Err` carries the response to send instead of handling the request;
+/// `Ok` the fault to apply while handling it.
+async fn prelude(
+    app: &AppState,
+    route: Route,
+    headers: &HeaderMap,
+    params: &HashMap<String, String>,
+) -> Result<Fault, Response> {
+    let authorized = match &app.expected_authorization {
+        None => true,
+        Some(expected) => {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                == Some(expected.as_str())
+        }
+    };
+
+    let (fault, latency) = {
+        let mut state = app.lock();
+        state.requests.push(RecordedRequest {
+            route,
+            prover_id: params.get("id").cloned(),
+            supported_vk_hashes: supported_vk_hashes(params),
+            wait_ms: wait_ms(params),
+            content_type: header(headers, CONTENT_TYPE),
+            content_encoding: header(headers, CONTENT_ENCODING),
+            accept_encoding: header(headers, ACCEPT_ENCODING),
+            authorization: header(headers, AUTHORIZATION),
+            idempotency_key: header(headers, IDEMPOTENCY_KEY),
+            traceparent: header(headers, TRACEPARENT),
+            authorized,
+        });
+        let fault = state
+            .faults
+            .get_mut(&route)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_default();
+        let latency = state.latencies.get(&route).copied().unwrap_or_default();
+        (fault, latency)
+    };
+
+    let delay = latency + fault.latency;
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    }
+    if let Some(status) = fault.status {
+        return Err(status.into_response());
+    }
+    Ok(fault)
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, message).into_response()
+}
+
+/// Answer a pick with `payload`: JSON, or binary frames (compressed as the client accepts)
+/// with the base64 `field` moved into frames if the client asked for those.
+fn job_response<P: serde::Serialize>(
+    headers: &HeaderMap,
+    payload: P,
+    field: &str,
+    fault: &Fault,
+) -> Response {
+    if !binary::accepts_binary(headers) {
+        return json_job_response(payload, fault);
+    }
+    let encoding = binary::response_encoding(headers);
+    let body = binary::encode(&payload, field).and_then(|body| match encoding {
+        Some(encoding) => binary::compress(encoding, body),
+        None => Ok(body),
+    });
+    match body {
+        Ok(body) => digested_response(binary::CONTENT_TYPE_BINARY, encoding, body, fault),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+fn json_job_response<P: serde::Serialize>(payload: P, fault: &Fault) -> Response {
+    let body = serde_json::to_vec(&payload).expect("payloads serialize to JSON");
+    digested_response("application/json", None, body, fault)
+}
+
+/// Answer with `body` and its `Content-Digest`, damaging the body afterwards if `fault`
+/// says so.
+fn digested_response(
+    content_type: &'static str,
+    encoding: Option<&str>,
+    mut body: Vec<u8>,
+    fault: &Fault,
+) -> Response {
+    let digest = binary::content_digest(&body);
+    if fault.corrupt_body {
+        let middle = body.len() / 2;
+        if let Some(byte) = body.get_mut(middle) {
+            *byte ^= 0x01;
+        }
+    }
+    let mut response = ([(CONTENT_TYPE, content_type)], body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static(binary::CONTENT_DIGEST),
+        digest.parse().expect("valid header value"),
+    );
+    if let Some(encoding) = encoding {
+        headers.insert(
+            CONTENT_ENCODING,
+            encoding.parse().expect("valid header value"),
+        );
+    }
+    response
+}
+
+/// Decode a submission body, JSON or binary frames carrying the base64 `field` raw.
+fn submission<P: serde::de::DeserializeOwned>(
+    headers: &HeaderMap,
+    body: &[u8],
+    field: &str,
+) -> anyhow::Result<P> {
+    binary::verify_digest(headers, body)?;
+    let body = binary::decompress(headers, body)?;
+    if binary::is_binary(headers) {
+        binary::decode(&body, field)
+    } else {
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+async fn info(State(app): State<AppState>, headers: HeaderMap, Query(params): Params) -> Response {
+    if let Err(response) = prelude(&app, Route::Info, &headers, &params).await {
+        return response;
+    }
+    match app.lock().capabilities.clone() {
+        Some(capabilities) => Json(capabilities).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn pick_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+) -> Response {
+    let fault = match prelude(&app, Route::PickFri, &headers, &params).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+    let supported = supported_vk_hashes(&params);
+
+    let payload = take_job(&app, &params, |state| {
+        let position = state.fri_jobs.iter().position(|job| {
+            supported
+                .as_ref()
+                .is_none_or(|hashes| hashes.contains(&job.vk_hash))
+        });
+        let job = position.and_then(|i| state.fri_jobs.remove(i))?;
+        let payload = FriJobPayload::from(&job);
+        state.picked_fri_jobs.insert(job.batch_number, job);
+        Some(payload)
+    })
+    .await;
+    match payload {
+        Some(payload) => job_response(&headers, payload, "prover_input", &fault),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn submit_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::SubmitFri, &headers, &params).await {
+        return response;
+    }
+    let payload: SubmitFriProofPayload = match submission(&headers, &body, "proof") {
+        Ok(payload) => payload,
+        Err(e) => return bad_request(format!("malformed FRI submission: {e}")),
+    };
+
+    tracing::info!(
+        "Mock sequencer received FRI proof for batch {} with vk {}",
+        payload.batch_number,
+        payload.vk_hash
+    );
+    let mut state = app.lock();
+    if !first_submission(&mut state, &headers) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    state.picked_fri_jobs.remove(&(payload.batch_number as u32));
+    state.fri_submissions.push(FriSubmission {
+        prover_id: params.get("id").cloned(),
+        batch_number: payload.batch_number,
+        vk_hash: payload.vk_hash,
+        proof: payload.proof,
+    });
+    StatusCode::OK.into_response()
+}
+
+/// Record the submission's idempotency key; `false` if it was seen before.
+fn first_submission(state: &mut MockState, headers: &HeaderMap) -> bool {
+    match header(headers, IDEMPOTENCY_KEY) {
+        Some(key) => state.idempotency_keys.insert(key),
+        None => true,
+    }
+}
+
+async fn peek_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(batch_number): Path<u32>,
+) -> Response {
+    let fault = match prelude(&app, Route::PeekFri, &headers, &params).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+
+    let state = app.lock();
+    let job = state.picked_fri_jobs.get(&batch_number).or_else(|| {
+        state
+            .fri_jobs
+            .iter()
+            .find(|job| job.batch_number == batch_number)
+    });
+    match job {
+        Some(job) => json_job_response(FriJobPayload::from(job), &fault),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn failed_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(batch_number): Path<u32>,
+) -> Response {
+    let fault = match prelude(&app, Route::FailedFri, &headers, &params).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+
+    match app.lock().failed_fri_proofs.get(&batch_number) {
+        Some(payload) => json_job_response(payload.clone(), &fault),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Failed proof report: retires the picked job and stores the report, served back by
+/// `GET FRI/{batch}/failed`.
+async fn submit_failed_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(batch_number): Path<u32>,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::SubmitFailedFri, &headers, &params).await {
+        return response;
+    }
+    let payload: FailedFriProofPayload = match submission(&headers, &body, "proof") {
+        Ok(payload) => payload,
+        Err(e) => return bad_request(format!("malformed failed FRI proof: {e}")),
+    };
+
+    tracing::info!("Mock sequencer received failed FRI proof for batch {batch_number}");
+    let mut state = app.lock();
+    if !first_submission(&mut state, &headers) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    state.picked_fri_jobs.remove(&batch_number);
+    state.failed_fri_proofs.insert(batch_number, payload);
+    StatusCode::OK.into_response()
+}
+
+async fn pick_snark(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+) -> Response {
+    let fault = match prelude(&app, Route::PickSnark, &headers, &params).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+    let supported = supported_vk_hashes(&params);
+
+    let payload = take_job(&app, &params, |state| {
+        let position = state.snark_jobs.iter().position(|job| {
+            supported
+                .as_ref()
+                .is_none_or(|hashes| hashes.contains(&job.vk_hash))
+        });
+        let job = position.and_then(|i| state.snark_jobs.remove(i))?;
+        let payload = SnarkJobPayload::from(&job);
+        state
+            .picked_snark_jobs
+            .insert((job.from_batch_number, job.to_batch_number), job);
+        Some(payload)
+    })
+    .await;
+    match payload {
+        Some(payload) => job_response(&headers, payload, "fri_proofs", &fault),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn submit_snark(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::SubmitSnark, &headers, &params).await {
+        return response;
+    }
+    let payload: SubmitSnarkProofPayload = match submission(&headers, &body, "proof") {
+        Ok(payload) => payload,
+        Err(e) => return bad_request(format!("malformed SNARK submission: {e}")),
+    };
+
+    tracing::info!(
+        "Mock sequencer received SNARK proof for batches [{}, {}] with vk {}",
+        payload.from_batch_number,
+        payload.to_batch_number,
+        payload.vk_hash
+    );
+    let mut state = app.lock();
+    if !first_submission(&mut state, &headers) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    state.picked_snark_jobs.remove(&(
+        payload.from_batch_number as u32,
+        payload.to_batch_number as u32,
+    ));
+    state.snark_submissions.push(SnarkSubmission {
+        prover_id: params.get("id").cloned(),
+        from_batch_number: payload.from_batch_number,
+        to_batch_number: payload.to_batch_number,
+        vk_hash: payload.vk_hash,
+        proof: payload.proof,
+    });
+    StatusCode::OK.into_response()
+}
+
+async fn peek_snark(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path((from_batch_number, to_batch_number)): Path<(u32, u32)>,
+) -> Response {
+    let fault = match prelude(&app, Route::PeekSnark, &headers, &params).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+
+    let state = app.lock();
+    let job = state
+        .picked_snark_jobs
+        .get(&(from_batch_number, to_batch_number))
+        .or_else(|| {
+            state.snark_jobs.iter().find(|job| {
+                job.from_batch_number == from_batch_number && job.to_batch_number == to_batch_number
+            })
+        });
+    match job {
+        Some(job) => json_job_response(SnarkJobPayload::from(job), &fault),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Lease extension: `200` while the job is picked, `410 Gone` once it was submitted or was
+/// never handed out.
+async fn heartbeat_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(batch_number): Path<u32>,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::HeartbeatFri, &headers, &params).await {
+        return response;
+    }
+
+    if app.lock().picked_fri_jobs.contains_key(&batch_number) {
+        StatusCode::OK.into_response()
+    } else {
+        StatusCode::GONE.into_response()
+    }
+}
+
+async fn heartbeat_snark(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(key): Path<(u32, u32)>,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::HeartbeatSnark, &headers, &params).await {
+        return response;
+    }
+
+    if app.lock().picked_snark_jobs.contains_key(&key) {
+        StatusCode::OK.into_response()
+    } else {
+        StatusCode::GONE.into_response()
+    }
+}
+
+/// Job release: a picked job goes back to the front of its queue (`200`); `410 Gone` if it
+/// isn't picked.
+async fn release_fri(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path(batch_number): Path<u32>,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::ReleaseFri, &headers, &params).await {
+        return response;
+    }
+    let payload: ReleaseJobPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return bad_request(format!("malformed job release: {e}")),
+    };
+
+    let mut state = app.lock();
+    let Some(job) = state.picked_fri_jobs.remove(&batch_number) else {
+        return StatusCode::GONE.into_response();
+    };
+    state.fri_jobs.push_front(job);
+    state.releases.push(JobRelease {
+        prover_id: params.get("id").cloned(),
+        job: ReleasedJob::Fri { batch_number },
+        reason: payload.reason,
+        message: payload.message,
+    });
+    StatusCode::OK.into_response()
+}
+
+async fn release_snark(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Params,
+    Path((from_batch_number, to_batch_number)): Path<(u32, u32)>,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = prelude(&app, Route::ReleaseSnark, &headers, &params).await {
+        return response;
+    }
+    let payload: ReleaseJobPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return bad_request(format!("malformed job release: {e}")),
+    };
+
+    let mut state = app.lock();
+    let Some(job) = state
+        .picked_snark_jobs
+        .remove(&(from_batch_number, to_batch_number))
+    else {
+        return StatusCode::GONE.into_response();
+    };
+    state.snark_jobs.push_front(job);
+    state.releases.push(JobRelease {
+        prover_id: params.get("id").cloned(),
+        job: ReleasedJob::Snark {
+            from_batch_number,
+            to_batch_number,
+        },
+        reason: payload.reason,
+        message: payload.message,
+    });
+    StatusCode::OK.into_response()
+}
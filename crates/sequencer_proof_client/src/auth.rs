@@ -0,0 +1,308 @@
+//! Authentication of requests to a sequencer.
+//!
+//! Every endpoint picks its own credentials (see [`crate::SequencerEndpoint::parse`]):
+//! Basic auth from the URL, a file or an environment variable, or a bearer token from a
+//! file. Client certificates for mutual TLS are set up in [`crate::SequencerProofClient::new`].
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::header::HeaderValue;
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::sequencer_endpoint::{CredentialSource, SequencerCredentials};
+use crate::SequencerEndpoint;
+
+/// Source of the `Authorization` header of requests to one sequencer.
+pub trait AuthProvider: fmt::Debug + Send + Sync {
+    /// Header value for the next request; `None` sends the request unauthenticated.
+    fn authorization(&self) -> Option<HeaderValue>;
+}
+
+/// The provider configured for `endpoint`, if it has credentials.
+pub(crate) fn endpoint_auth_provider(
+    endpoint: &SequencerEndpoint,
+) -> anyhow::Result<Option<Arc<dyn AuthProvider>>> {
+    let provider: Arc<dyn AuthProvider> = match (&endpoint.credentials, &endpoint.credential_source)
+    {
+        (None, None) => return Ok(None),
+        (Some(credentials), _) => Arc::new(StaticAuth::basic(credentials)?),
+        (None, Some(CredentialSource::BasicFile(path))) => {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read credentials file {}", path.display()))?;
+            let credentials = parse_basic_credentials(&contents)
+                .with_context(|| format!("Invalid credentials file {}", path.display()))?;
+            Arc::new(StaticAuth::basic(&credentials)?)
+        }
+        (None, Some(CredentialSource::BasicEnv(var))) => {
+            let contents = std::env::var(var)
+                .with_context(|| format!("Failed to read credentials from ${var}"))?;
+            let credentials = parse_basic_credentials(&contents)
+                .with_context(|| format!("Invalid credentials in ${var}"))?;
+            Arc::new(StaticAuth::basic(&credentials)?)
+        }
+        (None, Some(CredentialSource::BearerFile(path))) => Arc::new(BearerTokenFile::new(path)?),
+    };
+    Ok(Some(provider))
+}
+
+/// Parse `username:password`, as kept in credential files and variables.
+fn parse_basic_credentials(contents: &str) -> anyhow::Result<SequencerCredentials> {
+    let (username, password) = contents
+        .trim_end_matches(['\r', '\n'])
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected `username:password`"))?;
+    if username.is_empty() || password.is_empty() {
+        return Err(anyhow!("Username and password cannot be empty"));
+    }
+    Ok(SequencerCredentials {
+        username: username.to_string(),
+        password: SecretString::new(password.into()),
+    })
+}
+
+/// The same header on every request.
+struct StaticAuth(HeaderValue);
+
+impl StaticAuth {
+    fn basic(credentials: &SequencerCredentials) -> anyhow::Result<Self> {
+        let encoded = STANDARD.encode(format!(
+            "{}:{}",
+            credentials.username,
+            credentials.password.expose_secret()
+        ));
+        Ok(Self(sensitive_header(&format!("Basic {encoded}"))?))
+    }
+}
+
+impl fmt::Debug for StaticAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StaticAuth")
+    }
+}
+
+impl AuthProvider for StaticAuth {
+    fn authorization(&self) -> Option<HeaderValue> {
+        Some(self.0.clone())
+    }
+}
+
+fn sensitive_header(value: &str) -> anyhow::Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(value).context("Failed to create auth header value")?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Bumped on every SIGHUP; bearer token files are re-read when it changes.
+static RELOAD_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Make every [`BearerTokenFile`] re-read its token on its next request.
+pub(crate) fn reload_credentials() {
+    RELOAD_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Call [`reload_credentials`] on SIGHUP, once per process. Without a tokio runtime (or off
+/// unix) tokens are still reloaded when their file changes.
+fn listen_for_sighup() {
+    #[cfg(unix)]
+    {
+        static LISTENER: Once = Once::new();
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        LISTENER.call_once(|| {
+            runtime.spawn(async {
+                use tokio::signal::unix::{signal, SignalKind};
+                let mut hangups = match signal(SignalKind::hangup()) {
+                    Ok(hangups) => hangups,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to listen for SIGHUP, bearer tokens are only reloaded \
+                             when their files change: {e}"
+                        );
+                        return;
+                    }
+                };
+                while hangups.recv().await.is_some() {
+                    tracing::info!("SIGHUP received, reloading sequencer credentials");
+                    reload_credentials();
+                }
+            });
+        });
+    }
+}
+
+/// A bearer token kept in a file, so it can be rotated without restarting the prover:
+/// the file is re-read when its modification time changes or on SIGHUP. If re-reading
+/// fails, the previous token is kept.
+pub struct BearerTokenFile {
+    path: PathBuf,
+    state: Mutex<BearerTokenState>,
+}
+
+struct BearerTokenState {
+    header: HeaderValue,
+    modified: Option<SystemTime>,
+    generation: u64,
+}
+
+impl BearerTokenFile {
+    /// Read the token at `path`; fails if it can't be read, so misconfiguration shows at
+    /// startup rather than as rejected requests.
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        listen_for_sighup();
+        let generation = RELOAD_GENERATION.load(Ordering::Relaxed);
+        let modified = modified(path);
+        let header = read_bearer_token(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Mutex::new(BearerTokenState {
+                header,
+                modified,
+                generation,
+            }),
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn read_bearer_token(path: &Path) -> anyhow::Result<HeaderValue> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bearer token file {}", path.display()))?;
+    let token = contents.trim();
+    if token.is_empty() {
+        return Err(anyhow!("Bearer token file {} is empty", path.display()));
+    }
+    sensitive_header(&format!("Bearer {token}"))
+}
+
+impl fmt::Debug for BearerTokenFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerTokenFile")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthProvider for BearerTokenFile {
+    fn authorization(&self) -> Option<HeaderValue> {
+        let mut state = self.state.lock().expect("bearer token state poisoned");
+        let generation = RELOAD_GENERATION.load(Ordering::Relaxed);
+        let modified = modified(&self.path);
+        if generation != state.generation || modified != state.modified {
+            // Recorded even if reading fails, so a broken file is reported once per change.
+            state.generation = generation;
+            state.modified = modified;
+            match read_bearer_token(&self.path) {
+                Ok(header) => {
+                    tracing::info!("Reloaded bearer token from {}", self.path.display());
+                    state.header = header;
+                }
+                Err(e) => tracing::warn!("{e:#}; keeping the previous bearer token"),
+            }
+        }
+        Some(state.header.clone())
+    }
+}
+
+/// Command-line options of sequencer authentication, shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct AuthArgs {
+    /// Allow sending credentials to sequencers over plain HTTP. Without it, endpoints with
+    /// credentials must use HTTPS (or a loopback address).
+    #[arg(long, default_value_t = false)]
+    pub allow_insecure_credentials: bool,
+}
+
+impl AuthArgs {
+    pub fn apply(&self, endpoints: Vec<SequencerEndpoint>) -> Vec<SequencerEndpoint> {
+        endpoints
+            .into_iter()
+            .map(|endpoint| endpoint.allow_insecure_credentials(self.allow_insecure_credentials))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(endpoint: &str) -> anyhow::Result<Option<HeaderValue>> {
+        let endpoint = SequencerEndpoint::parse(endpoint)?;
+        Ok(endpoint_auth_provider(&endpoint)?.and_then(|provider| provider.authorization()))
+    }
+
+    #[test]
+    fn test_basic_credentials_from_file_and_env() {
+        let expected = format!("Basic {}", STANDARD.encode("prover:s3cret"));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials");
+        std::fs::write(&path, "prover:s3cret\n").unwrap();
+
+        let header = authorization(&format!(
+            "https://sequencer:3124#basic_file={}",
+            path.display()
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(header, expected.as_str());
+        assert!(header.is_sensitive());
+
+        std::env::set_var("TEST_SEQUENCER_CREDENTIALS", "prover:s3cret");
+        let header = authorization("https://sequencer:3124#basic_env=TEST_SEQUENCER_CREDENTIALS")
+            .unwrap()
+            .unwrap();
+        assert_eq!(header, expected.as_str());
+
+        assert!(authorization("https://sequencer:3124#basic_env=TEST_SEQUENCER_UNSET").is_err());
+        std::fs::write(&path, "no-password").unwrap();
+        assert!(authorization(&format!(
+            "https://sequencer:3124#basic_file={}",
+            path.display()
+        ))
+        .is_err());
+        assert_eq!(authorization("https://sequencer:3124").unwrap(), None);
+    }
+
+    #[test]
+    fn test_bearer_token_is_reloaded_on_change_and_sighup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "first\n").unwrap();
+        let provider = BearerTokenFile::new(&path).unwrap();
+        assert_eq!(provider.authorization().unwrap(), "Bearer first");
+
+        // A changed modification time triggers a reload.
+        std::fs::write(&path, "second").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(provider.authorization().unwrap(), "Bearer second");
+
+        // A broken file keeps the previous token.
+        std::fs::write(&path, "").unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(provider.authorization().unwrap(), "Bearer second");
+
+        // So does an unchanged file, until SIGHUP.
+        std::fs::write(&path, "third").unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(provider.authorization().unwrap(), "Bearer second");
+        reload_credentials();
+        assert_eq!(provider.authorization().unwrap(), "Bearer third");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(BearerTokenFile::new(&path).is_err());
+    }
+}
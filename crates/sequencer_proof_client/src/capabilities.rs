@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::PayloadEncoding;
+
+/// Prover API versions this client speaks, preferred first. Sequencers without the info
+/// route are assumed to serve the first one.
+pub const SUPPORTED_API_VERSIONS: &[&str] = &["v1"];
+
+/// An optional feature of the prover API, advertised by the sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequencerFeature {
+    /// Picks only hand out jobs of the versions in `supported_vk_hashes`.
+    SupportedVkHashes,
+    /// Job leases can be extended (`FRI/{batch}/heartbeat`, `SNARK/{from}/{to}/heartbeat`).
+    Heartbeats,
+    /// Picked jobs can be handed back (`FRI/{batch}/release`, `SNARK/{from}/{to}/release`).
+    Release,
+    /// Jobs and proofs can be sent as binary bodies instead of base64 in JSON.
+    BinaryPayloads,
+    /// Picks can wait for a job to become available instead of answering `204` right away.
+    LongPoll,
+    /// A feature this client doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// What a sequencer advertises on `GET /prover-jobs/info`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencerCapabilities {
+    /// Prover API version the sequencer serves, e.g. `v1`.
+    pub api_version: String,
+    /// VK hashes of the protocol versions the sequencer currently needs proofs for.
+    #[serde(default)]
+    pub active_vk_hashes: Vec<String>,
+    #[serde(default)]
+    pub features: Vec<SequencerFeature>,
+    /// `Content-Encoding`s the sequencer accepts on binary request bodies, e.g. `zstd`.
+    #[serde(default)]
+    pub content_encodings: Vec<String>,
+}
+
+impl SequencerCapabilities {
+    pub fn supports(&self, feature: SequencerFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// Compression for binary request bodies: the first of [`PayloadEncoding::SUPPORTED`]
+    /// the sequencer accepts.
+    pub fn upload_encoding(&self) -> PayloadEncoding {
+        PayloadEncoding::SUPPORTED
+            .iter()
+            .copied()
+            .find(|encoding| {
+                self.content_encodings
+                    .iter()
+                    .any(|name| PayloadEncoding::parse(name) == Some(*encoding))
+            })
+            .unwrap_or(PayloadEncoding::Identity)
+    }
+
+    /// Whether this client speaks the sequencer's API version.
+    pub fn is_api_version_supported(&self) -> bool {
+        SUPPORTED_API_VERSIONS.contains(&self.api_version.as_str())
+    }
+
+    /// Log what a prover supporting `supported_vk_hashes` (empty: any version) can expect
+    /// from the sequencer at `url`; loudly if it can't prove any job the sequencer has.
+    pub(crate) fn report(&self, url: &url::Url, supported_vk_hashes: &[String]) {
+        tracing::info!(
+            "Sequencer {url} serves prover API {} with features {:?}, active vk hashes: {:?}",
+            self.api_version,
+            self.features,
+            self.active_vk_hashes
+        );
+        if !supported_vk_hashes.is_empty() && !self.active_vk_hashes.is_empty() {
+            if !self
+                .active_vk_hashes
+                .iter()
+                .any(|hash| supported_vk_hashes.contains(hash))
+            {
+                tracing::error!(
+                    "This prover supports none of the protocol versions sequencer {url} needs \
+                     proofs for (active: {:?}, supported: {supported_vk_hashes:?}); it will not \
+                     get any jobs from it",
+                    self.active_vk_hashes
+                );
+            } else if !self.supports(SequencerFeature::SupportedVkHashes) {
+                tracing::warn!(
+                    "Sequencer {url} does not filter jobs by supported_vk_hashes; jobs of \
+                     unsupported versions will be picked and released"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_features_are_tolerated() {
+        let capabilities: SequencerCapabilities = serde_json::from_str(
+            r#"{"api_version":"v1","features":["heartbeats","quantum_proofs","long_poll"]}"#,
+        )
+        .unwrap();
+
+        assert!(capabilities.is_api_version_supported());
+        assert!(capabilities.active_vk_hashes.is_empty());
+        assert_eq!(capabilities.upload_encoding(), PayloadEncoding::Identity);
+        assert_eq!(
+            capabilities.features,
+            vec![
+                SequencerFeature::Heartbeats,
+                SequencerFeature::Unknown,
+                SequencerFeature::LongPoll
+            ]
+        );
+        assert!(capabilities.supports(SequencerFeature::LongPoll));
+        assert!(!capabilities.supports(SequencerFeature::Release));
+    }
+}
@@ -0,0 +1,322 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::task::JoinSet;
+use url::Url;
+use zkos_wrapper::SnarkWrapperProof;
+
+use crate::{
+    FailedFriProofPayload, FriJobInputs, L2BatchNumber, ProofClient, ProofClientError,
+    ReleaseReason, SequencerCapabilities, SnarkProofInputs,
+};
+
+type GroupMembers = Arc<Vec<Box<dyn ProofClient + Send + Sync>>>;
+
+/// One proof submission, replayable against any member of a group.
+type Submission = Arc<
+    dyn for<'a> Fn(
+            &'a (dyn ProofClient + Send + Sync),
+        )
+            -> Pin<Box<dyn Future<Output = Result<(), ProofClientError>> + Send + 'a>>
+        + Send
+        + Sync,
+>;
+
+fn submission<F>(submit: F) -> Submission
+where
+    F: for<'a> Fn(
+            &'a (dyn ProofClient + Send + Sync),
+        )
+            -> Pin<Box<dyn Future<Output = Result<(), ProofClientError>> + Send + 'a>>
+        + Send
+        + Sync
+        + 'static,
+{
+    Arc::new(submit)
+}
+
+/// How a proof is submitted across an endpoint group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupSubmitMode {
+    /// To the member the job came from first, then to the others in order, until one
+    /// accepts it.
+    Sequential,
+    /// To every member at once; the first acceptance wins.
+    Parallel,
+}
+
+/// [`ProofClient`] of one member of an endpoint group: sequencers (e.g. a primary and its
+/// replicas) sharing one job queue behind different URLs.
+///
+/// Everything but proof submissions goes to the member itself. A proof of a job picked
+/// from the member is submitted to the other members as well if the member doesn't accept
+/// it, so it isn't lost if that sequencer goes down while the job is proven. Submission
+/// stops at the first member accepting the proof; "already proven" counts as accepted,
+/// since the group's queue has the proof then.
+pub struct GroupMemberClient {
+    /// Index of this member in `members`.
+    position: usize,
+    members: GroupMembers,
+    mode: GroupSubmitMode,
+}
+
+impl std::fmt::Debug for GroupMemberClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let members: Vec<_> = self
+            .members
+            .iter()
+            .map(|member| member.sequencer_url().as_str())
+            .collect();
+        f.debug_struct("GroupMemberClient")
+            .field("url", &self.sequencer_url().as_str())
+            .field("members", &members)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl GroupMemberClient {
+    fn own(&self) -> &(dyn ProofClient + Send + Sync) {
+        self.members[self.position].as_ref()
+    }
+
+    /// Member indices in submission order: this member first.
+    fn submission_order(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(self.position)
+            .chain((0..self.members.len()).filter(move |&index| index != self.position))
+    }
+
+    async fn submit_to_group(&self, submit: Submission) -> Result<(), ProofClientError> {
+        // The member's own error is the one to report if no member accepts the proof.
+        let mut error = None;
+        let mut on_result = |index: usize, result: Result<(), ProofClientError>| {
+            let member = self.members[index].sequencer_url();
+            match result {
+                Ok(()) | Err(ProofClientError::AlreadyProven) => {
+                    if index != self.position {
+                        tracing::info!(
+                            "Proof for a job of {} was accepted by group member {member}",
+                            self.own().sequencer_url()
+                        );
+                    }
+                    true
+                }
+                Err(err) => {
+                    tracing::warn!("Group member {member} did not accept the proof: {err}");
+                    if index == self.position || error.is_none() {
+                        error = Some(err);
+                    }
+                    false
+                }
+            }
+        };
+
+        match self.mode {
+            GroupSubmitMode::Sequential => {
+                for index in self.submission_order() {
+                    if on_result(index, submit(self.members[index].as_ref()).await) {
+                        return Ok(());
+                    }
+                }
+            }
+            GroupSubmitMode::Parallel => {
+                let mut submissions = JoinSet::new();
+                let mut indices = HashMap::new();
+                for index in self.submission_order() {
+                    let members = self.members.clone();
+                    let submit = submit.clone();
+                    let task =
+                        submissions.spawn(async move { submit(members[index].as_ref()).await });
+                    indices.insert(task.id(), index);
+                }
+                while let Some(joined) = submissions.join_next_with_id().await {
+                    // A panicked submission counts as failed on that member; the others go on.
+                    let (index, result) = match joined {
+                        Ok((id, result)) => (indices[&id], result),
+                        Err(err) => (
+                            indices[&err.id()],
+                            Err(ProofClientError::Transport(anyhow::anyhow!(
+                                "group submission failed: {err}"
+                            ))),
+                        ),
+                    };
+                    if on_result(index, result) {
+                        // Let the others finish rather than cutting their uploads short;
+                        // they are accepted or answered with "already proven".
+                        submissions.detach_all();
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Err(error.expect("an endpoint group has at least one member"))
+    }
+}
+
+#[async_trait]
+impl ProofClient for GroupMemberClient {
+    fn sequencer_url(&self) -> &Url {
+        self.own().sequencer_url()
+    }
+
+    fn long_poll_wait(&self) -> Option<Duration> {
+        self.own().long_poll_wait()
+    }
+
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        self.own().negotiate_capabilities().await
+    }
+
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+        self.own().pick_fri_job().await
+    }
+
+    async fn submit_fri_proof(
+        &self,
+        batch_number: u32,
+        vk_hash: String,
+        proof: String,
+    ) -> Result<(), ProofClientError> {
+        self.submit_to_group(submission(move |client| {
+            client.submit_fri_proof(batch_number, vk_hash.clone(), proof.clone())
+        }))
+        .await
+    }
+
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        self.own().submit_failed_fri_proof(payload).await
+    }
+
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+        self.own().pick_snark_job().await
+    }
+
+    async fn submit_snark_proof(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
+        proof: SnarkWrapperProof,
+    ) -> Result<(), ProofClientError> {
+        self.submit_to_group(submission(move |client| {
+            client.submit_snark_proof(
+                from_batch_number,
+                to_batch_number,
+                vk_hash.clone(),
+                proof.clone(),
+            )
+        }))
+        .await
+    }
+
+    async fn heartbeat_fri_job(&self, batch_number: u32) -> Result<bool, ProofClientError> {
+        self.own().heartbeat_fri_job(batch_number).await
+    }
+
+    async fn heartbeat_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        self.own()
+            .heartbeat_snark_job(from_batch_number, to_batch_number)
+            .await
+    }
+
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.own()
+            .release_fri_job(batch_number, reason, message)
+            .await
+    }
+
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.own()
+            .release_snark_job(from_batch_number, to_batch_number, reason, message)
+            .await
+    }
+}
+
+/// Join the clients of endpoints sharing a group name (see
+/// [`crate::SequencerEndpoint::group`]) into [`GroupMemberClient`]s. `groups` has one entry
+/// per client, in the same order; clients without a group, or alone in theirs, are kept
+/// as they are.
+pub fn group_clients(
+    clients: Vec<Box<dyn ProofClient + Send + Sync>>,
+    groups: &[Option<String>],
+    mode: GroupSubmitMode,
+) -> Vec<Box<dyn ProofClient + Send + Sync>> {
+    assert_eq!(clients.len(), groups.len(), "one group entry per client");
+    let mut clients: Vec<_> = clients.into_iter().map(Some).collect();
+    let mut grouped: Vec<Option<Box<dyn ProofClient + Send + Sync>>> =
+        (0..clients.len()).map(|_| None).collect();
+
+    for (first, group) in groups.iter().enumerate() {
+        let Some(group) = group else { continue };
+        if clients[first].is_none() {
+            // Grouped along with an earlier member.
+            continue;
+        }
+        let indices: Vec<usize> = (first..groups.len())
+            .filter(|&index| groups[index].as_ref() == Some(group))
+            .collect();
+        if indices.len() < 2 {
+            tracing::warn!("Endpoint group {group:?} has a single member");
+            continue;
+        }
+        let members: GroupMembers = Arc::new(
+            indices
+                .iter()
+                .map(|&index| clients[index].take().expect("clients are grouped once"))
+                .collect(),
+        );
+        for (position, &index) in indices.iter().enumerate() {
+            grouped[index] = Some(Box::new(GroupMemberClient {
+                position,
+                members: members.clone(),
+                mode,
+            }));
+        }
+    }
+
+    grouped
+        .into_iter()
+        .zip(clients)
+        .map(|(grouped, client)| grouped.or(client).expect("every client is grouped or kept"))
+        .collect()
+}
+
+/// Command-line options of endpoint groups, shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct GroupArgs {
+    /// How proofs are submitted to the sequencers of an endpoint group (`#group=<name>`):
+    /// `sequential` tries the sequencer the job came from first, then the others in order;
+    /// `parallel` submits to all of them at once
+    #[arg(long, value_enum, default_value_t = GroupSubmitMode::Sequential)]
+    pub group_submit_mode: GroupSubmitMode,
+}
+
+impl GroupArgs {
+    pub fn apply(
+        &self,
+        clients: Vec<Box<dyn ProofClient + Send + Sync>>,
+        groups: &[Option<String>],
+    ) -> Vec<Box<dyn ProofClient + Send + Sync>> {
+        group_clients(clients, groups, self.group_submit_mode)
+    }
+}
@@ -0,0 +1,604 @@
+//! Versioned file format of jobs and proofs, read and written by the proof client CLI, the
+//! provers and [`crate::FileBasedProofClient`], so any of them can consume what another
+//! one saved.
+//!
+//! An envelope is a JSON header naming what it holds (`kind`) with that artifact's metadata
+//! (batch numbers, vk hash, ...), plus the artifact's binary blobs: the prover input of a
+//! FRI job, the FRI proofs of a SNARK job, or the proof of a proof file. It comes in three
+//! [`EnvelopeFormat`]s, told apart by [`EnvelopeFormat::detect`]:
+//! * `json` - the header with an `"envelope": 1` version field and the blobs in a `blobs`
+//!   array, base64-encoded;
+//! * `bin` - the framing of the binary transport (see [`crate::transport`]): the header
+//!   (with its version) as the first frame, then one frame per blob;
+//! * `b64` - the `bin` form, base64-encoded, for pasting through text-only channels.
+//!
+//! FRI proofs are kept bincode-serialized, as they are submitted; SNARK proofs as JSON, as
+//! `zkos_wrapper::serialize_to_file` writes them.
+
+use std::io::BufRead as _;
+use std::path::Path;
+
+use anyhow::Context as _;
+use base64::{engine::general_purpose::STANDARD, read::DecoderReader, Engine as _};
+use serde::{Deserialize, Serialize};
+use zkos_wrapper::SnarkWrapperProof;
+use zksync_airbender_execution_utils::unrolled::UnrolledProgramProof;
+
+use crate::transport::{decode_frames, encode_frames, read_frame};
+use crate::{
+    FailedFriProofPayload, FriJobInputs, L2BatchNumber, ProofClientError, SnarkProofInputs,
+};
+
+/// Version written to (and the only one read from) envelope headers.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Encoding of an envelope file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EnvelopeFormat {
+    /// A single JSON document, blobs base64-encoded.
+    Json,
+    /// Length-prefixed frames: the JSON header, then the raw blobs.
+    Bin,
+    /// The `bin` form, base64-encoded.
+    B64,
+}
+
+impl EnvelopeFormat {
+    /// The format of an envelope, if `data` looks like one.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        // A binary envelope opens with the big-endian length of its JSON header frame.
+        if let Some((len, rest)) = data.split_first_chunk::<4>() {
+            let len = u32::from_be_bytes(*len) as usize;
+            if len <= rest.len() && rest.first() == Some(&b'{') {
+                return Some(Self::Bin);
+            }
+        }
+        let text = data.trim_ascii();
+        if text.starts_with(b"{") {
+            Some(Self::Json)
+        } else if !text.is_empty()
+            && text
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='))
+        {
+            Some(Self::B64)
+        } else {
+            None
+        }
+    }
+
+    /// The format a file at `path` is written in by default: by its extension (`.bin`,
+    /// `.b64`), JSON otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bin") => Self::Bin,
+            Some("b64") => Self::B64,
+            _ => Self::Json,
+        }
+    }
+
+    /// File extension of the format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Bin => "bin",
+            Self::B64 => "b64",
+        }
+    }
+}
+
+/// What an envelope holds, with its metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EnvelopeHeader {
+    /// A FRI job; its blob is the prover input.
+    FriJob {
+        batch_number: u32,
+        vk_hash: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_hash_u32s: Option<[u32; 8]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_block_timestamp: Option<u64>,
+    },
+    /// A FRI proof; its blob is the proof.
+    FriProof { batch_number: u32, vk_hash: String },
+    /// A SNARK job; its blobs are the FRI proofs to aggregate.
+    SnarkJob {
+        from_batch_number: u32,
+        to_batch_number: u32,
+        vk_hash: String,
+    },
+    /// A SNARK proof; its blob is the proof.
+    SnarkProof {
+        from_batch_number: u32,
+        to_batch_number: u32,
+        vk_hash: String,
+    },
+    /// A FRI proof not matching its batch's expected hash; its blob is the proof.
+    FailedFriProof {
+        batch_number: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_block_timestamp: Option<u64>,
+        expected_hash_u32s: [u32; 8],
+        proof_final_register_values: [u32; 16],
+        vk_hash: String,
+    },
+}
+
+impl EnvelopeHeader {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::FriJob { .. } => "fri_job",
+            Self::FriProof { .. } => "fri_proof",
+            Self::SnarkJob { .. } => "snark_job",
+            Self::SnarkProof { .. } => "snark_proof",
+            Self::FailedFriProof { .. } => "failed_fri_proof",
+        }
+    }
+}
+
+/// Header as written to files, with the envelope version.
+#[derive(Serialize, Deserialize)]
+struct VersionedHeader<T> {
+    envelope: u32,
+    #[serde(flatten)]
+    header: EnvelopeHeader,
+    #[serde(flatten)]
+    rest: T,
+}
+
+/// Blobs of the JSON form.
+#[derive(Serialize, Deserialize)]
+struct JsonBlobs {
+    blobs: Vec<String>,
+}
+
+/// Nothing besides the header, in the header frame of the binary form.
+#[derive(Serialize, Deserialize)]
+struct NoBlobs {}
+
+/// A job or proof with its metadata, as kept in files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub header: EnvelopeHeader,
+    pub blobs: Vec<Vec<u8>>,
+}
+
+impl Envelope {
+    pub fn encode(&self, format: EnvelopeFormat) -> Vec<u8> {
+        match format {
+            EnvelopeFormat::Json => serde_json::to_vec_pretty(&VersionedHeader {
+                envelope: ENVELOPE_VERSION,
+                header: self.header.clone(),
+                rest: JsonBlobs {
+                    blobs: self
+                        .blobs
+                        .iter()
+                        .map(|blob| STANDARD.encode(blob))
+                        .collect(),
+                },
+            })
+            .expect("envelopes serialize to JSON"),
+            EnvelopeFormat::Bin => {
+                let blobs: Vec<&[u8]> = self.blobs.iter().map(Vec::as_slice).collect();
+                let header = VersionedHeader {
+                    envelope: ENVELOPE_VERSION,
+                    header: self.header.clone(),
+                    rest: NoBlobs {},
+                };
+                encode_frames(&header, &blobs)
+            }
+            EnvelopeFormat::B64 => {
+                let mut encoded = STANDARD.encode(self.encode(EnvelopeFormat::Bin));
+                encoded.push('\n');
+                encoded.into_bytes()
+            }
+        }
+    }
+
+    /// Decode an envelope in any of the [`EnvelopeFormat`]s.
+    pub fn decode(data: &[u8]) -> Result<Self, ProofClientError> {
+        let malformed = |e: &dyn std::fmt::Display| {
+            ProofClientError::MalformedPayload(format!("malformed envelope: {e}"))
+        };
+        let (header, blobs) = match EnvelopeFormat::detect(data) {
+            None => {
+                return Err(ProofClientError::MalformedPayload(
+                    "not an envelope (JSON, binary or base64)".to_string(),
+                ))
+            }
+            Some(EnvelopeFormat::Json) => {
+                let envelope: VersionedHeader<JsonBlobs> =
+                    serde_json::from_slice(data).map_err(|e| malformed(&e))?;
+                let blobs = envelope
+                    .rest
+                    .blobs
+                    .iter()
+                    .map(|blob| STANDARD.decode(blob))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| malformed(&e))?;
+                check_version(envelope.envelope)?;
+                (envelope.header, blobs)
+            }
+            Some(EnvelopeFormat::Bin) => {
+                let (envelope, blobs) = decode_frames::<VersionedHeader<NoBlobs>>(data)?;
+                check_version(envelope.envelope)?;
+                (envelope.header, blobs)
+            }
+            Some(EnvelopeFormat::B64) => {
+                let data = STANDARD
+                    .decode(data.trim_ascii())
+                    .map_err(|e| malformed(&e))?;
+                if EnvelopeFormat::detect(&data) != Some(EnvelopeFormat::Bin) {
+                    return Err(malformed(&"base64 text is not a binary envelope"));
+                }
+                return Self::decode(&data);
+            }
+        };
+        Ok(Self { header, blobs })
+    }
+
+    /// Read an envelope file, detecting its format.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))?;
+        Self::decode(&data).with_context(|| format!("Failed to decode {path:?}"))
+    }
+
+    /// Write the envelope to `path` in `format`.
+    pub fn write(&self, path: &Path, format: EnvelopeFormat) -> anyhow::Result<()> {
+        std::fs::write(path, self.encode(format))
+            .with_context(|| format!("Failed to write {path:?}"))
+    }
+
+    /// The single blob of a one-blob kind.
+    fn into_blob(self) -> Result<(EnvelopeHeader, Vec<u8>), ProofClientError> {
+        let kind = self.header.kind();
+        let Ok([blob]) = <[Vec<u8>; 1]>::try_from(self.blobs) else {
+            return Err(ProofClientError::MalformedPayload(format!(
+                "{kind} envelope must carry exactly one blob"
+            )));
+        };
+        Ok((self.header, blob))
+    }
+}
+
+/// The `vk_hash` of the envelope at `path` (or, in a JSON file, of the sequencer response it
+/// holds), read without decoding its blobs: enough to filter queued jobs by version.
+pub(crate) fn read_vk_hash(path: &Path) -> anyhow::Result<String> {
+    #[derive(Deserialize)]
+    struct VkHash {
+        vk_hash: String,
+    }
+
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    let mut reader = std::io::BufReader::new(file);
+    let format = EnvelopeFormat::detect(reader.fill_buf()?)
+        .with_context(|| format!("{path:?} is not an envelope"))?;
+    let header = match format {
+        // Skips the blobs without decoding them.
+        EnvelopeFormat::Json => return Ok(serde_json::from_reader::<_, VkHash>(reader)?.vk_hash),
+        EnvelopeFormat::Bin => read_frame(&mut reader)?,
+        EnvelopeFormat::B64 => read_frame(&mut DecoderReader::new(reader, &STANDARD))?,
+    };
+    let header = header.with_context(|| format!("{path:?} has no header frame"))?;
+    Ok(serde_json::from_slice::<VkHash>(&header)?.vk_hash)
+}
+
+fn check_version(version: u32) -> Result<(), ProofClientError> {
+    if version == ENVELOPE_VERSION {
+        Ok(())
+    } else {
+        Err(ProofClientError::MalformedPayload(format!(
+            "unsupported envelope version {version}, expected {ENVELOPE_VERSION}"
+        )))
+    }
+}
+
+fn wrong_kind(expected: &str, header: &EnvelopeHeader) -> ProofClientError {
+    ProofClientError::MalformedPayload(format!(
+        "expected a {expected} envelope, found {}",
+        header.kind()
+    ))
+}
+
+/// A job or proof that can be kept in an [`Envelope`].
+pub trait Enveloped: Sized {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError>;
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError>;
+
+    /// Write `self` to `path`; in `format` if given, otherwise by the path's extension.
+    fn write_to(&self, path: &Path, format: Option<EnvelopeFormat>) -> anyhow::Result<()> {
+        let format = format.unwrap_or_else(|| EnvelopeFormat::for_path(path));
+        self.to_envelope()?.write(path, format)
+    }
+
+    /// Read an envelope of this kind from `path`, in any format.
+    fn read_from(path: &Path) -> anyhow::Result<Self> {
+        Self::from_envelope(Envelope::read(path)?)
+            .with_context(|| format!("Unexpected contents of {path:?}"))
+    }
+}
+
+impl Enveloped for FriJobInputs {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError> {
+        Ok(Envelope {
+            header: EnvelopeHeader::FriJob {
+                batch_number: self.batch_number,
+                vk_hash: self.vk_hash.clone(),
+                expected_hash_u32s: self.expected_hash_u32s,
+                last_block_timestamp: self.last_block_timestamp,
+            },
+            blobs: vec![self.prover_input.clone()],
+        })
+    }
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError> {
+        match envelope.into_blob()? {
+            (
+                EnvelopeHeader::FriJob {
+                    batch_number,
+                    vk_hash,
+                    expected_hash_u32s,
+                    last_block_timestamp,
+                },
+                prover_input,
+            ) => Ok(Self {
+                batch_number,
+                vk_hash,
+                prover_input,
+                expected_hash_u32s,
+                last_block_timestamp,
+            }),
+            (header, _) => Err(wrong_kind("fri_job", &header)),
+        }
+    }
+}
+
+/// A FRI proof of a batch, as the FRI prover saves it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriProofFile {
+    pub batch_number: u32,
+    pub vk_hash: String,
+    /// The bincode-serialized proof.
+    pub proof: Vec<u8>,
+}
+
+impl FriProofFile {
+    /// The proof, base64-encoded as [`crate::ProofClient::submit_fri_proof`] takes it.
+    pub fn proof_base64(&self) -> String {
+        STANDARD.encode(&self.proof)
+    }
+
+    /// The deserialized proof.
+    pub fn decode_proof(&self) -> Result<UnrolledProgramProof, ProofClientError> {
+        bincode::serde::decode_from_slice(&self.proof, bincode::config::standard())
+            .map(|(proof, _)| proof)
+            .map_err(|e| {
+                ProofClientError::MalformedPayload(format!(
+                    "FRI proof of batch {}: {e}",
+                    self.batch_number
+                ))
+            })
+    }
+}
+
+impl Enveloped for FriProofFile {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError> {
+        Ok(Envelope {
+            header: EnvelopeHeader::FriProof {
+                batch_number: self.batch_number,
+                vk_hash: self.vk_hash.clone(),
+            },
+            blobs: vec![self.proof.clone()],
+        })
+    }
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError> {
+        match envelope.into_blob()? {
+            (
+                EnvelopeHeader::FriProof {
+                    batch_number,
+                    vk_hash,
+                },
+                proof,
+            ) => Ok(Self {
+                batch_number,
+                vk_hash,
+                proof,
+            }),
+            (header, _) => Err(wrong_kind("fri_proof", &header)),
+        }
+    }
+}
+
+impl Enveloped for SnarkProofInputs {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError> {
+        let blobs = self
+            .fri_proofs
+            .iter()
+            .map(|proof| bincode::serde::encode_to_vec(proof, bincode::config::standard()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                ProofClientError::MalformedPayload(format!("Failed to serialize FRI proof: {e}"))
+            })?;
+        Ok(Envelope {
+            header: EnvelopeHeader::SnarkJob {
+                from_batch_number: self.from_batch_number.0,
+                to_batch_number: self.to_batch_number.0,
+                vk_hash: self.vk_hash.clone(),
+            },
+            blobs,
+        })
+    }
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError> {
+        let EnvelopeHeader::SnarkJob {
+            from_batch_number,
+            to_batch_number,
+            vk_hash,
+        } = envelope.header
+        else {
+            return Err(wrong_kind("snark_job", &envelope.header));
+        };
+        let fri_proofs = envelope
+            .blobs
+            .iter()
+            .enumerate()
+            .map(|(i, blob)| {
+                bincode::serde::decode_from_slice(blob, bincode::config::standard())
+                    .map(|(proof, _)| proof)
+                    .map_err(|e| ProofClientError::MalformedPayload(format!("FRI proof {i}: {e}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            from_batch_number: L2BatchNumber(from_batch_number),
+            to_batch_number: L2BatchNumber(to_batch_number),
+            vk_hash,
+            fri_proofs,
+        })
+    }
+}
+
+/// A SNARK proof of a batch range, as the SNARK prover saves it.
+#[derive(Debug, Clone)]
+pub struct SnarkProofFile {
+    pub from_batch_number: L2BatchNumber,
+    pub to_batch_number: L2BatchNumber,
+    pub vk_hash: String,
+    pub proof: SnarkWrapperProof,
+}
+
+impl Enveloped for SnarkProofFile {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError> {
+        Ok(Envelope {
+            header: EnvelopeHeader::SnarkProof {
+                from_batch_number: self.from_batch_number.0,
+                to_batch_number: self.to_batch_number.0,
+                vk_hash: self.vk_hash.clone(),
+            },
+            blobs: vec![serde_json::to_vec(&self.proof).expect("SNARK proofs serialize to JSON")],
+        })
+    }
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError> {
+        match envelope.into_blob()? {
+            (
+                EnvelopeHeader::SnarkProof {
+                    from_batch_number,
+                    to_batch_number,
+                    vk_hash,
+                },
+                proof,
+            ) => Ok(Self {
+                from_batch_number: L2BatchNumber(from_batch_number),
+                to_batch_number: L2BatchNumber(to_batch_number),
+                vk_hash,
+                proof: serde_json::from_slice(&proof).map_err(|e| {
+                    ProofClientError::MalformedPayload(format!("malformed SNARK proof: {e}"))
+                })?,
+            }),
+            (header, _) => Err(wrong_kind("snark_proof", &header)),
+        }
+    }
+}
+
+impl Enveloped for FailedFriProofPayload {
+    fn to_envelope(&self) -> Result<Envelope, ProofClientError> {
+        let proof = STANDARD.decode(&self.proof).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("failed FRI proof is not base64: {e}"))
+        })?;
+        Ok(Envelope {
+            header: EnvelopeHeader::FailedFriProof {
+                batch_number: self.batch_number,
+                last_block_timestamp: self.last_block_timestamp,
+                expected_hash_u32s: self.expected_hash_u32s,
+                proof_final_register_values: self.proof_final_register_values,
+                vk_hash: self.vk_hash.clone(),
+            },
+            blobs: vec![proof],
+        })
+    }
+
+    fn from_envelope(envelope: Envelope) -> Result<Self, ProofClientError> {
+        match envelope.into_blob()? {
+            (
+                EnvelopeHeader::FailedFriProof {
+                    batch_number,
+                    last_block_timestamp,
+                    expected_hash_u32s,
+                    proof_final_register_values,
+                    vk_hash,
+                },
+                proof,
+            ) => Ok(Self {
+                batch_number,
+                last_block_timestamp,
+                expected_hash_u32s,
+                proof_final_register_values,
+                vk_hash,
+                proof: STANDARD.encode(proof),
+            }),
+            (header, _) => Err(wrong_kind("failed_fri_proof", &header)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelopes_round_trip_through_every_format() {
+        let job = FriJobInputs {
+            batch_number: 7,
+            vk_hash: "0xaa".to_string(),
+            prover_input: vec![1, 2, 3, 4],
+            expected_hash_u32s: Some([5; 8]),
+            last_block_timestamp: None,
+        };
+        let envelope = job.to_envelope().unwrap();
+        for format in [
+            EnvelopeFormat::Json,
+            EnvelopeFormat::Bin,
+            EnvelopeFormat::B64,
+        ] {
+            let encoded = envelope.encode(format);
+            assert_eq!(EnvelopeFormat::detect(&encoded), Some(format));
+            let decoded = Envelope::decode(&encoded).unwrap();
+            assert_eq!(decoded, envelope);
+            let decoded = FriJobInputs::from_envelope(decoded).unwrap();
+            assert_eq!(decoded.prover_input, job.prover_input);
+            assert_eq!(decoded.expected_hash_u32s, job.expected_hash_u32s);
+        }
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&envelope.encode(EnvelopeFormat::Json)).unwrap();
+        assert_eq!(json["envelope"], 1);
+        assert_eq!(json["kind"], "fri_job");
+        assert_eq!(json["blobs"][0], "AQIDBA==");
+    }
+
+    #[test]
+    fn test_mismatched_envelopes_are_rejected() {
+        let proof = FriProofFile {
+            batch_number: 7,
+            vk_hash: "0xaa".to_string(),
+            proof: b"proof".to_vec(),
+        };
+        let envelope = proof.to_envelope().unwrap();
+        assert!(matches!(
+            FriJobInputs::from_envelope(envelope.clone()),
+            Err(ProofClientError::MalformedPayload(_))
+        ));
+
+        let mut json: serde_json::Value =
+            serde_json::from_slice(&envelope.encode(EnvelopeFormat::Json)).unwrap();
+        json["envelope"] = 2.into();
+        let err = Envelope::decode(&serde_json::to_vec(&json).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("version 2"), "{err}");
+
+        assert!(Envelope::decode(b"").is_err());
+        assert!(Envelope::decode(b"[1, 2, 3]").is_err());
+    }
+}
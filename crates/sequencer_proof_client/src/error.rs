@@ -0,0 +1,116 @@
+use reqwest::StatusCode;
+use vise::{EncodeLabelSet, EncodeLabelValue};
+
+/// Failure of a [`crate::ProofClient`] call, classified so callers can react to the cause
+/// (retry, drop the job, alert on credentials) without inspecting transport internals.
+#[derive(Debug, thiserror::Error)]
+pub enum ProofClientError {
+    /// No response within the client's request timeout.
+    #[error("request timed out: {0}")]
+    Timeout(reqwest::Error),
+    /// The sequencer rejected the client's credentials (401 / 403).
+    #[error("unauthorized: sequencer returned {0}")]
+    Unauthorized(StatusCode),
+    /// The job was already proven, e.g. by another prover that picked it after a lease
+    /// expiry (409). Proof submissions answered with 409 count as accepted instead.
+    #[error("job was already proven")]
+    AlreadyProven,
+    /// The job's lease expired and the sequencer no longer accepts a proof for it (410).
+    #[error("job lease expired")]
+    LeaseExpired,
+    /// The sequencer does not accept the protocol version (vk hash) of the request (422).
+    #[error("protocol version is not supported by the sequencer")]
+    UnsupportedVersion,
+    /// A request or response body could not be encoded or decoded.
+    #[error("malformed payload: {0}")]
+    MalformedPayload(String),
+    /// A received body does not match the digest its sender announced for it, so it was
+    /// corrupted on the way.
+    #[error("content digest mismatch: expected sha-256 {expected}, got {actual}")]
+    DigestMismatch { expected: String, actual: String },
+    /// Any other non-success status.
+    #[error("sequencer returned {0}")]
+    ServerError(StatusCode),
+    /// The request could not be delivered: connection failures, or I/O errors for
+    /// file-based clients.
+    #[error("transport error: {0:#}")]
+    Transport(anyhow::Error),
+}
+
+impl ProofClientError {
+    /// Classify a non-success response status.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized(status),
+            StatusCode::CONFLICT => Self::AlreadyProven,
+            StatusCode::GONE => Self::LeaseExpired,
+            StatusCode::UNPROCESSABLE_ENTITY => Self::UnsupportedVersion,
+            StatusCode::BAD_REQUEST => {
+                Self::MalformedPayload(format!("sequencer rejected the request body ({status})"))
+            }
+            _ => Self::ServerError(status),
+        }
+    }
+
+    /// Whether the same call may succeed if repeated: timeouts, connection failures, bodies
+    /// corrupted in transit and server-side errors (5xx, 429). Everything else would fail the same way again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout(_) | Self::Transport(_) | Self::DigestMismatch { .. } => true,
+            Self::ServerError(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::Unauthorized(_)
+            | Self::AlreadyProven
+            | Self::LeaseExpired
+            | Self::UnsupportedVersion
+            | Self::MalformedPayload(_) => false,
+        }
+    }
+
+    /// Metric label of this error.
+    pub fn kind(&self) -> ProofClientErrorKind {
+        match self {
+            Self::Timeout(_) => ProofClientErrorKind::Timeout,
+            Self::Unauthorized(_) => ProofClientErrorKind::Unauthorized,
+            Self::AlreadyProven => ProofClientErrorKind::AlreadyProven,
+            Self::LeaseExpired => ProofClientErrorKind::LeaseExpired,
+            Self::UnsupportedVersion => ProofClientErrorKind::UnsupportedVersion,
+            Self::MalformedPayload(_) => ProofClientErrorKind::MalformedPayload,
+            Self::DigestMismatch { .. } => ProofClientErrorKind::DigestMismatch,
+            Self::ServerError(_) => ProofClientErrorKind::ServerError,
+            Self::Transport(_) => ProofClientErrorKind::Transport,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ProofClientError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout(err)
+        } else if err.is_decode() {
+            Self::MalformedPayload(err.to_string())
+        } else if let Some(status) = err.status() {
+            Self::from_status(status)
+        } else {
+            Self::Transport(err.into())
+        }
+    }
+}
+
+/// [`ProofClientError`] variant, without payload; used as a metric label.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, EncodeLabelValue, EncodeLabelSet,
+)]
+#[metrics(label = "error", rename_all = "snake_case")]
+pub enum ProofClientErrorKind {
+    Timeout,
+    Unauthorized,
+    AlreadyProven,
+    LeaseExpired,
+    UnsupportedVersion,
+    MalformedPayload,
+    DigestMismatch,
+    ServerError,
+    Transport,
+}
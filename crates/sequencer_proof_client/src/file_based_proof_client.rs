@@ -1,151 +1,634 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::de::DeserializeOwned;
+use url::Url;
 use zkos_wrapper::SnarkWrapperProof;
 
+use crate::envelope::read_vk_hash;
 use crate::{
-    FailedFriProofPayload, GetSnarkProofPayload, L2BlockNumber, NextFriProverJobPayload,
-    ProofClient, SnarkProofInputs, SubmitFriProofPayload, SubmitSnarkProofPayload,
+    Envelope, EnvelopeFormat, Enveloped, FailedFriProofPayload, FriJobInputs, FriProofFile,
+    GetSnarkProofPayload, L2BatchNumber, NextFriProverJobPayload, ProofClient, ProofClientError,
+    ReleaseReason, SnarkProofFile, SnarkProofInputs,
 };
 
-const FRI_JOB_FILE: &str = "fri_job.json";
-const FRI_PROOF_FILE: &str = "fri_proof.json";
-const SNARK_JOB_FILE: &str = "snark_job.json";
-const SNARK_PROOF_FILE: &str = "snark_proof.json";
-const FAILED_FRI_PROOF_FILE: &str = "failed_fri_proof.json";
+const INBOX_DIR: &str = "inbox";
+const PICKED_DIR: &str = "picked";
+const OUTBOX_DIR: &str = "outbox";
+const FAILED_DIR: &str = "failed";
 
-// FileBasedProofClient stores proof jobs and proofs in files, useful for local testing.
-#[derive(Debug)]
+/// Extensions of queued files, one per [`EnvelopeFormat`].
+const EXTENSIONS: [&str; 3] = ["json", "bin", "b64"];
+
+/// A [`ProofClient`] backed by a directory queue instead of a sequencer, so provers can run
+/// fully offline from a set of captured jobs.
+///
+/// Layout of `base_dir`:
+/// * `inbox/` - pending jobs, one [`Envelope`] per job in any format: `fri_<batch>.<ext>`
+///   and `snark_<from>_<to>.<ext>`, `<ext>` being `json`, `bin` or `b64`. JSON files may
+///   also hold the sequencer's pick response (`{batch_number, vk_hash, prover_input}` for
+///   FRI jobs), as captured before envelopes.
+/// * `picked/` - jobs handed out by a pick. Jobs are moved here from `inbox/` with an atomic
+///   rename, so several provers can share one queue without picking a job twice.
+/// * `outbox/` - submitted proofs, as JSON envelopes named like the job they prove. A submit
+///   removes the job from `picked/`.
+/// * `failed/` - reported failed FRI proofs, as JSON envelopes named like the job. A report
+///   removes the job from `picked/` too. Jobs that can't be read are moved here as well,
+///   named `<job file>.unreadable`.
+///
+/// Released jobs are moved from `picked/` back to `inbox/`, to be picked again.
+///
+/// Jobs are picked in batch order.
+#[derive(Debug, Clone)]
 pub struct FileBasedProofClient {
-    pub base_dir: PathBuf,
+    base_dir: PathBuf,
+    url: Url,
+    supported_vk_hashes: Vec<String>,
 }
 
-impl Default for FileBasedProofClient {
-    fn default() -> Self {
-        Self {
-            base_dir: PathBuf::from("./outputs/"),
+impl FileBasedProofClient {
+    /// Create a client over the queue at `base_dir`, creating its directories if needed.
+    ///
+    /// `supported_vk_hashes` filters picks like the sequencer does; empty means any version.
+    ///
+    /// # Errors
+    /// * if the directories cannot be created
+    pub fn new(base_dir: PathBuf, supported_vk_hashes: Vec<String>) -> anyhow::Result<Self> {
+        for dir in [INBOX_DIR, PICKED_DIR, OUTBOX_DIR, FAILED_DIR] {
+            let path = base_dir.join(dir);
+            std::fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create queue directory {path:?}"))?;
         }
+        let base_dir = base_dir
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve queue directory {base_dir:?}"))?;
+        let url = Url::from_directory_path(&base_dir)
+            .map_err(|_| anyhow!("Queue directory {base_dir:?} cannot be expressed as a URL"))?;
+        Ok(Self {
+            base_dir,
+            url,
+            supported_vk_hashes,
+        })
     }
-}
 
-impl FileBasedProofClient {
-    pub fn new(base_dir: String) -> Self {
-        Self {
-            base_dir: PathBuf::from(base_dir),
+    /// Create a client from a `file://` URL pointing at the queue directory.
+    pub fn from_url(url: &Url, supported_vk_hashes: Vec<String>) -> anyhow::Result<Self> {
+        let base_dir = url
+            .to_file_path()
+            .map_err(|_| anyhow!("Not a local file URL: {url}"))?;
+        Self::new(base_dir, supported_vk_hashes)
+    }
+
+    /// Queue a FRI job in `inbox/`, e.g. one captured from a sequencer.
+    pub fn enqueue_fri_job(&self, job: &FriJobInputs) -> anyhow::Result<PathBuf> {
+        let path = self
+            .base_dir
+            .join(INBOX_DIR)
+            .join(file_name(&fri_stem(job.batch_number)));
+        write_atomically(&path, &job.to_envelope()?)?;
+        Ok(path)
+    }
+
+    /// Queue a SNARK job in `inbox/`, e.g. one captured from a sequencer.
+    pub fn enqueue_snark_job(&self, job: &SnarkProofInputs) -> anyhow::Result<PathBuf> {
+        let path = self.base_dir.join(INBOX_DIR).join(file_name(&snark_stem(
+            job.from_batch_number,
+            job.to_batch_number,
+        )));
+        write_atomically(&path, &job.to_envelope()?)?;
+        Ok(path)
+    }
+
+    fn is_supported(&self, vk_hash: &str) -> bool {
+        self.supported_vk_hashes.is_empty() || self.supported_vk_hashes.iter().any(|h| h == vk_hash)
+    }
+
+    /// Move the first supported job with the given prefix from `inbox/` to `picked/` and
+    /// return it. A job renamed away by a concurrent picker is skipped; one that can't be
+    /// read is moved to `failed/`.
+    ///
+    /// Jobs are filtered by their `vk_hash` alone and only read in full once picked. They
+    /// are read from envelopes, or from `L`, the sequencer's pick response. Blocking; see
+    /// [`Self::pick_blocking`].
+    fn pick<T, L, K>(&self, prefix: &str) -> Result<Option<T>, ProofClientError>
+    where
+        T: Enveloped,
+        L: DeserializeOwned + TryInto<T, Error = ProofClientError>,
+        K: Ord + FromFileName,
+    {
+        let inbox = self.base_dir.join(INBOX_DIR);
+        let mut jobs = std::fs::read_dir(&inbox)
+            .with_context(|| format!("Failed to list {inbox:?}"))
+            .map_err(ProofClientError::Transport)?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let key = K::from_file_name(&name, prefix)?;
+                Some((key, name))
+            })
+            .collect::<Vec<_>>();
+        jobs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (_, name) in jobs {
+            let path = inbox.join(&name);
+            match read_vk_hash(&path) {
+                Ok(vk_hash) if self.is_supported(&vk_hash) => {}
+                Ok(_) => continue,
+                // Picked by someone else between listing and reading.
+                Err(_) if !path.exists() => continue,
+                Err(e) => {
+                    self.set_aside(&path, &e);
+                    continue;
+                }
+            }
+            let picked = self.base_dir.join(PICKED_DIR).join(&name);
+            match std::fs::rename(&path, &picked) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(ProofClientError::Transport(
+                        anyhow::Error::new(e).context(format!("Failed to pick {path:?}")),
+                    ))
+                }
+            }
+            match read_job::<T, L>(&picked) {
+                Ok(job) => return Ok(Some(job)),
+                Err(e) => self.set_aside(&picked, &e),
+            }
         }
+        Ok(None)
     }
 
-    pub fn serialize_snark_proof(&self, proof: &SnarkWrapperProof) -> anyhow::Result<String> {
-        let path = self.base_dir.join(SNARK_PROOF_FILE);
-        let mut file =
-            std::fs::File::create(path).context(format!("Failed to create {SNARK_PROOF_FILE}"))?;
-        serde_json::to_writer_pretty(&mut file, &proof)
-            .context(format!("Failed to write {SNARK_PROOF_FILE}"))?;
-        Ok(String::new())
-    }
-
-    pub fn serialize_fri_job(&self, batch_number: u32, prover_input: &[u8]) -> anyhow::Result<()> {
-        let path = self.base_dir.join(FRI_JOB_FILE);
-        let mut file =
-            std::fs::File::create(path).context(format!("Failed to create {FRI_JOB_FILE}"))?;
-        serde_json::to_writer_pretty(
-            &mut file,
-            &NextFriProverJobPayload {
-                block_number,
-                prover_input: STANDARD.encode(prover_input),
-            },
-        )
-        .context(format!("Failed to write {FRI_JOB_FILE}"))?;
-        Ok(())
+    /// [`Self::pick`] on a blocking thread: picking reads and decodes whole jobs.
+    async fn pick_blocking<T, L, K>(
+        &self,
+        prefix: &'static str,
+    ) -> Result<Option<T>, ProofClientError>
+    where
+        T: Enveloped + Send + 'static,
+        L: DeserializeOwned + TryInto<T, Error = ProofClientError>,
+        K: Ord + FromFileName,
+    {
+        let client = self.clone();
+        tokio::task::spawn_blocking(move || client.pick::<T, L, K>(prefix))
+            .await
+            .map_err(|e| ProofClientError::Transport(anyhow!("Queue pick task failed: {e}")))?
     }
 
-    pub fn serialize_snark_job(&self, snark_proof_inputs: &SnarkProofInputs) -> anyhow::Result<()> {
-        let path = self.base_dir.join(SNARK_JOB_FILE);
-        let mut file =
-            std::fs::File::create(path).context(format!("Failed to create {SNARK_JOB_FILE}"))?;
-        serde_json::to_writer_pretty(&mut file, &snark_proof_inputs)
-            .context(format!("Failed to write {SNARK_JOB_FILE}"))?;
-        Ok(())
+    /// Move an unreadable job to `failed/`, so it isn't picked again.
+    fn set_aside(&self, path: &Path, error: &dyn std::fmt::Display) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let aside = self
+            .base_dir
+            .join(FAILED_DIR)
+            .join(format!("{name}.unreadable"));
+        tracing::warn!("Moving unreadable job {path:?} to {aside:?}: {error:#}");
+        if let Err(e) = std::fs::rename(path, &aside) {
+            tracing::warn!("Failed to move {path:?} aside: {e}");
+        }
     }
 
-    pub fn serialize_failed_fri_proof(
+    /// Write a submission to `dir` (`outbox/` or `failed/`) and retire the job named `stem`
+    /// from `picked/`. Blocking; see [`Self::submit_blocking`].
+    fn submit(
         &self,
-        failed_fri_proof: &FailedFriProofPayload,
-    ) -> anyhow::Result<()> {
-        let path = self.base_dir.join(FAILED_FRI_PROOF_FILE);
-        let mut file = std::fs::File::create(path)
-            .context(format!("Failed to create {FAILED_FRI_PROOF_FILE}"))?;
-        serde_json::to_writer_pretty(&mut file, &failed_fri_proof)
-            .context(format!("Failed to write {FAILED_FRI_PROOF_FILE}"))?;
+        dir: &str,
+        stem: &str,
+        submission: &impl Enveloped,
+    ) -> Result<(), ProofClientError> {
+        let envelope = submission.to_envelope()?;
+        write_atomically(&self.base_dir.join(dir).join(file_name(stem)), &envelope)
+            .map_err(ProofClientError::Transport)?;
+        for extension in EXTENSIONS {
+            let picked = self
+                .base_dir
+                .join(PICKED_DIR)
+                .join(format!("{stem}.{extension}"));
+            match std::fs::remove_file(&picked) {
+                Ok(()) => {}
+                // Proofs for jobs that were never picked from this queue are still accepted.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(ProofClientError::Transport(
+                        anyhow::Error::new(e)
+                            .context(format!("Failed to retire picked job {picked:?}")),
+                    ))
+                }
+            }
+        }
         Ok(())
     }
 
-    pub fn deserialize_failed_fri_proof(&self) -> anyhow::Result<FailedFriProofPayload> {
-        let path = self.base_dir.join(FAILED_FRI_PROOF_FILE);
-        let file =
-            std::fs::File::open(path).context(format!("Failed to open {FAILED_FRI_PROOF_FILE}"))?;
-        let failed_fri_proof: FailedFriProofPayload = serde_json::from_reader(file)
-            .context(format!("Failed to parse {FAILED_FRI_PROOF_FILE}"))?;
-        Ok(failed_fri_proof)
+    /// [`Self::submit`] on a blocking thread: submitting writes and syncs whole proofs.
+    async fn submit_blocking(
+        &self,
+        dir: &'static str,
+        stem: String,
+        submission: impl Enveloped + Send + 'static,
+    ) -> Result<(), ProofClientError> {
+        let client = self.clone();
+        tokio::task::spawn_blocking(move || client.submit(dir, &stem, &submission))
+            .await
+            .map_err(|e| ProofClientError::Transport(anyhow!("Queue submit task failed: {e}")))?
+    }
+
+    /// Move the job named `stem` from `picked/` back to `inbox/`. Fails with
+    /// [`ProofClientError::LeaseExpired`] if no such job is picked. Blocking; see
+    /// [`Self::release_blocking`].
+    fn release(
+        &self,
+        stem: &str,
+        reason: ReleaseReason,
+        message: &str,
+    ) -> Result<bool, ProofClientError> {
+        let mut released = false;
+        for extension in EXTENSIONS {
+            let name = format!("{stem}.{extension}");
+            let picked = self.base_dir.join(PICKED_DIR).join(&name);
+            let inbox = self.base_dir.join(INBOX_DIR).join(&name);
+            match std::fs::rename(&picked, &inbox) {
+                Ok(()) => {
+                    tracing::info!("Released {picked:?} ({reason:?}): {message}");
+                    released = true;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(ProofClientError::Transport(
+                        anyhow::Error::new(e)
+                            .context(format!("Failed to release picked job {picked:?}")),
+                    ))
+                }
+            }
+        }
+        if !released {
+            return Err(ProofClientError::LeaseExpired);
+        }
+        Ok(true)
+    }
+
+    /// [`Self::release`] on a blocking thread, like picks and submissions.
+    async fn release_blocking(
+        &self,
+        stem: String,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        let client = self.clone();
+        tokio::task::spawn_blocking(move || client.release(&stem, reason, &message))
+            .await
+            .map_err(|e| ProofClientError::Transport(anyhow!("Queue release task failed: {e}")))?
+    }
+}
+
+/// Sort key of a queued job, parsed from its file name.
+trait FromFileName: Sized {
+    fn from_file_name(name: &str, prefix: &str) -> Option<Self>;
+}
+
+/// The part of a queued file's name between `prefix` and its extension.
+fn file_name_key<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let (stem, extension) = name.strip_prefix(prefix)?.rsplit_once('.')?;
+    EXTENSIONS.contains(&extension).then_some(stem)
+}
+
+impl FromFileName for u32 {
+    fn from_file_name(name: &str, prefix: &str) -> Option<Self> {
+        file_name_key(name, prefix)?.parse().ok()
+    }
+}
+
+impl FromFileName for (u32, u32) {
+    fn from_file_name(name: &str, prefix: &str) -> Option<Self> {
+        let (from, to) = file_name_key(name, prefix)?.split_once('_')?;
+        Some((from.parse().ok()?, to.parse().ok()?))
+    }
+}
+
+fn fri_stem(batch_number: u32) -> String {
+    format!("fri_{batch_number}")
+}
+
+fn snark_stem(from_batch_number: L2BatchNumber, to_batch_number: L2BatchNumber) -> String {
+    format!("snark_{from_batch_number}_{to_batch_number}")
+}
+
+/// Name of a file the queue writes: a JSON envelope.
+fn file_name(stem: &str) -> String {
+    format!("{stem}.{}", EnvelopeFormat::Json.extension())
+}
+
+/// Read a queued job: an envelope in any format, or the sequencer's pick response `L`.
+fn read_job<T, L>(path: &Path) -> Result<T, ProofClientError>
+where
+    T: Enveloped,
+    L: DeserializeOwned + TryInto<T, Error = ProofClientError>,
+{
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read {path:?}"))
+        .map_err(ProofClientError::Transport)?;
+    let malformed =
+        |e: ProofClientError| ProofClientError::MalformedPayload(format!("{path:?}: {e}"));
+    match Envelope::decode(&data) {
+        Ok(envelope) => T::from_envelope(envelope).map_err(malformed),
+        Err(e) => match serde_json::from_slice::<L>(&data) {
+            Ok(payload) => payload.try_into().map_err(malformed),
+            Err(_) => Err(malformed(e)),
+        },
     }
 }
 
+/// Write `envelope` as JSON next to `path` and rename it into place, so readers never see
+/// a partially written file.
+fn write_atomically(path: &Path, envelope: &Envelope) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    envelope.write(&tmp_path, EnvelopeFormat::Json)?;
+    std::fs::rename(&tmp_path, path).with_context(|| format!("Failed to move {path:?} into place"))
+}
+
 #[async_trait]
 impl ProofClient for FileBasedProofClient {
-    fn sequencer_url(&self) -> &str {
-        "file-based"
-    }
-
-    async fn pick_fri_job(&self) -> anyhow::Result<Option<(u32, Vec<u8>)>> {
-        let path = self.base_dir.join(FRI_JOB_FILE);
-        let file = std::fs::File::open(path).context(format!("Failed to open {FRI_JOB_FILE}"))?;
-        let fri_job: NextFriProverJobPayload =
-            serde_json::from_reader(file).context(format!("Failed to parse {FRI_JOB_FILE}"))?;
-        let data = STANDARD
-            .decode(&fri_job.prover_input)
-            .map_err(|e| anyhow!("Failed to decode block data: {e}"))?;
-        Ok(Some((fri_job.block_number, data)))
-    }
-
-    async fn submit_fri_proof(&self, block_number: u32, proof: String) -> anyhow::Result<()> {
-        let path = self.base_dir.join(FRI_PROOF_FILE);
-        let mut file =
-            std::fs::File::create(path).context(format!("Failed to create {FRI_PROOF_FILE}"))?;
-        let payload = SubmitFriProofPayload {
-            block_number: block_number as u64,
-            proof,
-        };
-        serde_json::to_writer_pretty(&mut file, &payload)
-            .context(format!("Failed to write {FRI_PROOF_FILE}"))?;
-        Ok(())
+    fn sequencer_url(&self) -> &Url {
+        &self.url
     }
 
-    async fn pick_snark_job(&self) -> anyhow::Result<Option<SnarkProofInputs>> {
-        let path = self.base_dir.join(SNARK_JOB_FILE);
-        let file = std::fs::File::open(path).context(format!("Failed to open {SNARK_JOB_FILE}"))?;
-        let snark_job: GetSnarkProofPayload =
-            serde_json::from_reader(file).context(format!("Failed to parse {SNARK_JOB_FILE}"))?;
-        Ok(Some(snark_job.try_into()?))
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+        self.pick_blocking::<FriJobInputs, NextFriProverJobPayload, u32>("fri_")
+            .await
+    }
+
+    async fn submit_fri_proof(
+        &self,
+        batch_number: u32,
+        vk_hash: String,
+        proof: String,
+    ) -> Result<(), ProofClientError> {
+        let proof = STANDARD.decode(&proof).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("FRI proof is not base64: {e}"))
+        })?;
+        self.submit_blocking(
+            OUTBOX_DIR,
+            fri_stem(batch_number),
+            FriProofFile {
+                batch_number,
+                vk_hash,
+                proof,
+            },
+        )
+        .await
+    }
+
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        let batch_number = u32::try_from(payload.batch_number).map_err(|_| {
+            ProofClientError::MalformedPayload(format!(
+                "batch number {} does not fit into u32",
+                payload.batch_number
+            ))
+        })?;
+        self.submit_blocking(FAILED_DIR, fri_stem(batch_number), payload)
+            .await
+    }
+
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+        self.pick_blocking::<SnarkProofInputs, GetSnarkProofPayload, (u32, u32)>("snark_")
+            .await
     }
 
     async fn submit_snark_proof(
         &self,
-        from_block_number: L2BlockNumber,
-        to_block_number: L2BlockNumber,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
         proof: SnarkWrapperProof,
-    ) -> anyhow::Result<()> {
-        let path = self.base_dir.join(SNARK_PROOF_FILE);
-        let mut file =
-            std::fs::File::create(path).context(format!("Failed to create {SNARK_PROOF_FILE}"))?;
-        let payload = SubmitSnarkProofPayload {
-            block_number_from: from_block_number.0 as u64,
-            block_number_to: to_block_number.0 as u64,
-            proof: self.serialize_snark_proof(&proof)?,
+    ) -> Result<(), ProofClientError> {
+        self.submit_blocking(
+            OUTBOX_DIR,
+            snark_stem(from_batch_number, to_batch_number),
+            SnarkProofFile {
+                from_batch_number,
+                to_batch_number,
+                vk_hash,
+                proof,
+            },
+        )
+        .await
+    }
+
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.release_blocking(fri_stem(batch_number), reason, message)
+            .await
+    }
+
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.release_blocking(
+            snark_stem(from_batch_number, to_batch_number),
+            reason,
+            message,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fri_job(batch_number: u32, vk_hash: &str) -> FriJobInputs {
+        FriJobInputs {
+            batch_number,
+            vk_hash: vk_hash.to_string(),
+            prover_input: vec![batch_number as u8; 8],
+            expected_hash_u32s: None,
+            last_block_timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fri_jobs_move_through_the_queue_in_batch_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = FileBasedProofClient::new(dir.path().to_path_buf(), vec![]).unwrap();
+        client.enqueue_fri_job(&fri_job(10, "0xaa")).unwrap();
+        client.enqueue_fri_job(&fri_job(9, "0xaa")).unwrap();
+
+        let job = client.pick_fri_job().await.unwrap().unwrap();
+        assert_eq!(job.batch_number, 9);
+        assert_eq!(job.vk_hash, "0xaa");
+        assert_eq!(job.prover_input, vec![9; 8]);
+        assert!(dir.path().join("picked/fri_9.json").exists());
+        assert!(!dir.path().join("inbox/fri_9.json").exists());
+
+        client
+            .submit_fri_proof(9, "0xaa".to_string(), STANDARD.encode("proof"))
+            .await
+            .unwrap();
+        assert!(!dir.path().join("picked/fri_9.json").exists());
+        let submitted = FriProofFile::read_from(&dir.path().join("outbox/fri_9.json")).unwrap();
+        assert_eq!(submitted.batch_number, 9);
+        assert_eq!(submitted.vk_hash, "0xaa");
+        assert_eq!(submitted.proof, b"proof");
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().batch_number,
+            10
+        );
+        assert!(client.pick_fri_job().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_released_jobs_are_picked_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = FileBasedProofClient::new(dir.path().to_path_buf(), vec![]).unwrap();
+        client.enqueue_fri_job(&fri_job(7, "0xaa")).unwrap();
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().batch_number,
+            7
+        );
+        assert!(client.pick_fri_job().await.unwrap().is_none());
+
+        assert!(client
+            .release_fri_job(7, ReleaseReason::Busy, "busy".to_string())
+            .await
+            .unwrap());
+        assert!(!dir.path().join("picked/fri_7.json").exists());
+        assert!(dir.path().join("inbox/fri_7.json").exists());
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().batch_number,
+            7
+        );
+
+        // Nothing is released for a job that isn't picked.
+        assert!(matches!(
+            client
+                .release_fri_job(8, ReleaseReason::Busy, "busy".to_string())
+                .await,
+            Err(ProofClientError::LeaseExpired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_vk_hashes_stay_in_the_inbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let client =
+            FileBasedProofClient::new(dir.path().to_path_buf(), vec!["0xbb".to_string()]).unwrap();
+        client.enqueue_fri_job(&fri_job(1, "0xaa")).unwrap();
+        client.enqueue_fri_job(&fri_job(2, "0xbb")).unwrap();
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().batch_number,
+            2
+        );
+        assert!(client.pick_fri_job().await.unwrap().is_none());
+        assert!(dir.path().join("inbox/fri_1.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_read_from_any_envelope_format_and_pick_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = FileBasedProofClient::new(dir.path().to_path_buf(), vec![]).unwrap();
+        let legacy = NextFriProverJobPayload {
+            batch_number: 1,
+            vk_hash: "0xaa".to_string(),
+            prover_input: STANDARD.encode([1; 8]),
+            expected_hash_u32s: None,
+            last_block_timestamp: None,
         };
-        serde_json::to_writer_pretty(&mut file, &payload)
-            .context(format!("Failed to write {SNARK_PROOF_FILE}"))?;
-        Ok(())
+        std::fs::write(
+            dir.path().join("inbox/fri_1.json"),
+            serde_json::to_vec(&legacy).unwrap(),
+        )
+        .unwrap();
+        fri_job(2, "0xaa")
+            .write_to(&dir.path().join("inbox/fri_2.bin"), None)
+            .unwrap();
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().prover_input,
+            vec![1; 8]
+        );
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().prover_input,
+            vec![2; 8]
+        );
+        assert!(dir.path().join("picked/fri_2.bin").exists());
+
+        client
+            .submit_fri_proof(2, "0xaa".to_string(), STANDARD.encode("proof"))
+            .await
+            .unwrap();
+        assert!(!dir.path().join("picked/fri_2.bin").exists());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_jobs_are_moved_to_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let client =
+            FileBasedProofClient::new(dir.path().to_path_buf(), vec!["0xbb".to_string()]).unwrap();
+        std::fs::write(dir.path().join("inbox/fri_1.json"), b"{\"batch_num").unwrap();
+        let mut foreign: serde_json::Value = serde_json::from_slice(
+            &fri_job(2, "0xbb")
+                .to_envelope()
+                .unwrap()
+                .encode(EnvelopeFormat::Json),
+        )
+        .unwrap();
+        foreign["envelope"] = 2.into();
+        std::fs::write(
+            dir.path().join("inbox/fri_2.json"),
+            serde_json::to_vec(&foreign).unwrap(),
+        )
+        .unwrap();
+        fri_job(3, "0xaa")
+            .write_to(&dir.path().join("inbox/fri_3.b64"), None)
+            .unwrap();
+        fri_job(4, "0xbb")
+            .write_to(&dir.path().join("inbox/fri_4.b64"), None)
+            .unwrap();
+
+        assert_eq!(
+            client.pick_fri_job().await.unwrap().unwrap().batch_number,
+            4
+        );
+        assert!(client.pick_fri_job().await.unwrap().is_none());
+        assert!(dir.path().join("failed/fri_1.json.unreadable").exists());
+        assert!(dir.path().join("failed/fri_2.json.unreadable").exists());
+        // Filtered out by its vk hash, not unreadable.
+        assert!(dir.path().join("inbox/fri_3.b64").exists());
+    }
+
+    #[tokio::test]
+    async fn test_snark_job_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = Url::from_directory_path(dir.path()).unwrap();
+        let client = FileBasedProofClient::from_url(&url, vec![]).unwrap();
+        client
+            .enqueue_snark_job(&SnarkProofInputs {
+                from_batch_number: L2BatchNumber(3),
+                to_batch_number: L2BatchNumber(4),
+                vk_hash: "0xaa".to_string(),
+                fri_proofs: vec![],
+            })
+            .unwrap();
+
+        let job = client.pick_snark_job().await.unwrap().unwrap();
+        assert_eq!(job.from_batch_number, L2BatchNumber(3));
+        assert_eq!(job.to_batch_number, L2BatchNumber(4));
+        assert!(dir.path().join("picked/snark_3_4.json").exists());
+        assert_eq!(client.sequencer_url().scheme(), "file");
     }
 }
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+/// How long a [`crate::SequencerProofClient`] asks a long-polling sequencer to hold a pick
+/// request open while it has no job.
+pub const DEFAULT_LONG_POLL_WAIT: Duration = Duration::from_secs(10);
+
+/// Sleep between empty polls of sequencers that can't long-poll: starts short, so a job
+/// queued right after an empty poll is picked up quickly, and doubles up to a cap while
+/// the sequencer stays idle.
+#[derive(Debug, Clone)]
+pub struct IdleBackoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl IdleBackoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    /// Time to sleep after an empty poll. A sequencer that long-polled already waited for a
+    /// job, so it is polled again after the initial delay.
+    pub fn next_delay(&mut self, long_polled: bool) -> Duration {
+        if long_polled {
+            self.reset();
+            return self.initial;
+        }
+        let delay = self.current;
+        self.current = self.current.saturating_mul(2).min(self.max);
+        delay
+    }
+
+    /// A job was found (or the sequencer did the waiting): start over.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+/// Command-line options of the provers' idle polling, shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct IdleArgs {
+    /// Sleep after the first empty poll of a sequencer that can't long-poll, in milliseconds;
+    /// doubled after every following empty poll
+    #[arg(long, default_value_t = 100)]
+    pub idle_backoff_initial_ms: u64,
+    /// Upper bound for the sleep between empty polls, in milliseconds
+    #[arg(long, default_value_t = 5_000)]
+    pub idle_backoff_max_ms: u64,
+}
+
+impl IdleArgs {
+    pub fn backoff(&self) -> IdleBackoff {
+        IdleBackoff::new(
+            Duration::from_millis(self.idle_backoff_initial_ms),
+            Duration::from_millis(self.idle_backoff_max_ms),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_backoff_doubles_up_to_max_and_resets() {
+        let mut backoff = IdleBackoff::new(Duration::from_millis(100), Duration::from_millis(500));
+
+        let delays: Vec<_> = (0..5)
+            .map(|_| backoff.next_delay(false).as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(false), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(false), Duration::from_millis(200));
+        // Long-polled sequencers did the waiting themselves.
+        assert_eq!(backoff.next_delay(true), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(false), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{fmt, time::Duration};
+
+use tokio::{runtime::RuntimeFlavor, sync::oneshot};
+use tracing::Instrument as _;
+
+use crate::{L2BatchNumber, ProofClient, ProofClientError, ReleaseReason};
+
+/// How often provers extend the lease of the job they are proving.
+pub const LEASE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A picked job, held under a lease until it is submitted or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeasedJob {
+    Fri {
+        batch_number: u32,
+    },
+    Snark {
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    },
+}
+
+impl LeasedJob {
+    /// Hand the job back to the sequencer with `reason`, so it is reassigned without
+    /// waiting for the lease to expire. Best effort: failures are only logged, since the
+    /// lease expiry reclaims the job anyway.
+    pub async fn release(&self, client: &dyn ProofClient, reason: ReleaseReason, message: String) {
+        let result = match *self {
+            Self::Fri { batch_number } => {
+                client.release_fri_job(batch_number, reason, message).await
+            }
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+            } => {
+                client
+                    .release_snark_job(from_batch_number, to_batch_number, reason, message)
+                    .await
+            }
+        };
+        match result {
+            Ok(true) => tracing::info!(
+                "Released {self} back to sequencer {} ({reason:?})",
+                client.sequencer_url()
+            ),
+            Ok(false) => tracing::debug!(
+                "Sequencer {} does not support releasing jobs, {self} is reassigned once its \
+                 lease expires",
+                client.sequencer_url()
+            ),
+            Err(e) => tracing::warn!(
+                "Failed to release {self} back to sequencer {}: {e}",
+                client.sequencer_url()
+            ),
+        }
+    }
+
+    async fn heartbeat(&self, client: &dyn ProofClient) -> Result<bool, ProofClientError> {
+        match *self {
+            Self::Fri { batch_number } => client.heartbeat_fri_job(batch_number).await,
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+            } => {
+                client
+                    .heartbeat_snark_job(from_batch_number, to_batch_number)
+                    .await
+            }
+        }
+    }
+}
+
+impl fmt::Display for LeasedJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fri { batch_number } => write!(f, "FRI job for batch {batch_number}"),
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+            } => write!(
+                f,
+                "SNARK job for batches {from_batch_number} to {to_batch_number}"
+            ),
+        }
+    }
+}
+
+/// Run the blocking `work` (i.e. proving `job`) while extending the job's lease on the
+/// sequencer every `interval`, so it isn't reassigned to another prover mid-proof.
+///
+/// Proving is synchronous and holds its thread for minutes, so `work` runs in
+/// [`tokio::task::block_in_place`], handing the thread's other tasks to another worker, and
+/// the heartbeats are sent from a scoped thread driving them on the current tokio runtime.
+/// Heartbeats stop as soon as `work` returns, or for good if the sequencer doesn't support
+/// them or reports the lease as already lost.
+///
+/// # Panics
+/// * if called outside of a multi-threaded tokio runtime
+pub fn with_lease_heartbeat<T>(
+    client: &dyn ProofClient,
+    job: LeasedJob,
+    interval: Duration,
+    work: impl FnOnce() -> T,
+) -> T {
+    let runtime = tokio::runtime::Handle::current();
+    assert_eq!(
+        runtime.runtime_flavor(),
+        RuntimeFlavor::MultiThread,
+        "lease heartbeats need a multi-threaded runtime to run alongside the work"
+    );
+    let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+    // Heartbeats belong to the job's span, like the requests `work` makes.
+    let span = tracing::Span::current();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            runtime.block_on(heartbeat_loop(client, job, interval, stop_receiver).instrument(span))
+        });
+        let result = tokio::task::block_in_place(work);
+        drop(stop_sender);
+        result
+    })
+}
+
+async fn heartbeat_loop(
+    client: &dyn ProofClient,
+    job: LeasedJob,
+    interval: Duration,
+    mut stop_receiver: oneshot::Receiver<()>,
+) {
+    loop {
+        tokio::select! {
+            _ = &mut stop_receiver => return,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        match job.heartbeat(client).await {
+            Ok(true) => tracing::debug!(
+                "Extended lease of {job} on sequencer {}",
+                client.sequencer_url()
+            ),
+            Ok(false) => {
+                tracing::info!(
+                    "Sequencer {} does not support lease heartbeats, not extending lease of {job}",
+                    client.sequencer_url()
+                );
+                return;
+            }
+            Err(ProofClientError::LeaseExpired) => {
+                tracing::warn!(
+                    "Lease of {job} on sequencer {} is lost; the job may be reassigned and \
+                     its proof rejected",
+                    client.sequencer_url()
+                );
+                return;
+            }
+            Err(e) => tracing::warn!(
+                "Failed to extend lease of {job} on sequencer {}: {e}",
+                client.sequencer_url()
+            ),
+        }
+    }
+}
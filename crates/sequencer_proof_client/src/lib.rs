@@ -1,22 +1,57 @@
-// TODO: Currently disabled as it's not used anywhere. Needs a rework anyways.
-// pub mod file_based_proof_client;
-
+pub mod auth;
+pub mod capabilities;
+pub mod endpoint_group;
+pub mod envelope;
+pub mod error;
+pub mod file_based_proof_client;
+pub mod idle_backoff;
+pub mod lease_heartbeat;
+pub mod proof_spool;
+pub mod retrying_proof_client;
 pub mod sequencer_endpoint;
+pub mod sequencer_pool;
 pub mod sequencer_proof_client;
+pub mod telemetry;
+pub mod timeouts;
+pub mod transport;
 
-pub use sequencer_endpoint::SequencerEndpoint;
+pub use auth::{AuthArgs, AuthProvider, BearerTokenFile};
+pub use capabilities::{SequencerCapabilities, SequencerFeature, SUPPORTED_API_VERSIONS};
+pub use endpoint_group::{group_clients, GroupArgs, GroupMemberClient, GroupSubmitMode};
+pub use envelope::{
+    Envelope, EnvelopeFormat, EnvelopeHeader, Enveloped, FriProofFile, SnarkProofFile,
+    ENVELOPE_VERSION,
+};
+pub use error::{ProofClientError, ProofClientErrorKind};
+pub use file_based_proof_client::FileBasedProofClient;
+pub use idle_backoff::{IdleArgs, IdleBackoff, DEFAULT_LONG_POLL_WAIT};
+pub use lease_heartbeat::{with_lease_heartbeat, LeasedJob, LEASE_HEARTBEAT_INTERVAL};
+pub use proof_spool::{ProofSpool, SpoolArgs, SpooledProof};
+pub use retrying_proof_client::{RetryArgs, RetryPolicy, RetryingProofClient};
+pub use sequencer_endpoint::{EndpointScheduling, SequencerEndpoint};
+pub use sequencer_pool::{
+    CircuitBreakerPolicy, ConcurrentPickArgs, EndpointState, PickErrorHandler, PoolArgs,
+    SequencerPool,
+};
 pub use sequencer_proof_client::SequencerProofClient;
+pub use telemetry::{init_tracing, TelemetryArgs, TelemetryGuard};
+pub use timeouts::{RequestTimeouts, TimeoutArgs, DEFAULT_REQUEST_TIMEOUT};
+pub use transport::PayloadEncoding;
 
 use crate::metrics::SEQUENCER_CLIENT_METRICS;
+use crate::snark_job_decoder::FriProofDecoder;
 use async_trait::async_trait;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 use std::fmt;
+use std::time::Duration;
 use url::Url;
 use zkos_wrapper::SnarkWrapperProof;
 use zksync_airbender_execution_utils::unrolled::UnrolledProgramProof;
 
 mod metrics;
+mod snark_job_decoder;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, PartialOrd, Ord)]
 pub struct L2BatchNumber(pub u32);
@@ -32,6 +67,10 @@ struct NextFriProverJobPayload {
     batch_number: u32,
     vk_hash: String,
     prover_input: String, // base64-encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expected_hash_u32s: Option<[u32; 8]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_block_timestamp: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,6 +80,12 @@ struct SubmitFriProofPayload {
     proof: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct ReleaseJobPayload {
+    reason: ReleaseReason,
+    message: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GetSnarkProofPayload {
     from_batch_number: u64,
@@ -57,46 +102,165 @@ struct SubmitSnarkProofPayload {
     proof: String, // base64‑encoded SNARK proof
 }
 
+/// Header frame of a binary FRI job; the prover input follows (see [`transport`]).
 #[derive(Debug, Serialize, Deserialize)]
+struct FriJobHeader {
+    batch_number: u32,
+    vk_hash: String,
+    #[serde(default)]
+    expected_hash_u32s: Option<[u32; 8]>,
+    #[serde(default)]
+    last_block_timestamp: Option<u64>,
+}
+
+/// Header frame of a binary FRI proof submission; the proof follows.
+#[derive(Debug, Serialize, Deserialize)]
+struct FriProofHeader {
+    batch_number: u64,
+    vk_hash: String,
+}
+
+/// Header frame of a binary SNARK job, followed by its FRI proofs, or of a binary SNARK
+/// proof submission, followed by the proof.
+#[derive(Debug, Serialize, Deserialize)]
+struct SnarkJobHeader {
+    from_batch_number: u64,
+    to_batch_number: u64,
+    vk_hash: String,
+}
+
+/// Diagnostics of a FRI proof whose output doesn't match the batch's expected hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FailedFriProofPayload {
     pub batch_number: u64,
-    pub last_block_timestamp: u64,
+    /// Timestamp of the batch's last block, if the job carried it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_block_timestamp: Option<u64>,
     pub expected_hash_u32s: [u32; 8],
     pub proof_final_register_values: [u32; 16],
     pub vk_hash: String,
     pub proof: String, // base64‑encoded FRI proof
 }
 
+impl TryInto<FriJobInputs> for NextFriProverJobPayload {
+    type Error = ProofClientError;
+
+    fn try_into(self) -> Result<FriJobInputs, Self::Error> {
+        let prover_input = STANDARD.decode(&self.prover_input).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("Failed to decode batch data: {e}"))
+        })?;
+        Ok(FriJobInputs {
+            batch_number: self.batch_number,
+            vk_hash: self.vk_hash,
+            prover_input,
+            expected_hash_u32s: self.expected_hash_u32s,
+            last_block_timestamp: self.last_block_timestamp,
+        })
+    }
+}
+
+impl FriJobHeader {
+    /// Job inputs from the header and the frames after it.
+    fn into_inputs(self, blobs: Vec<Vec<u8>>) -> Result<FriJobInputs, ProofClientError> {
+        let Ok([prover_input]) = <[Vec<u8>; 1]>::try_from(blobs) else {
+            return Err(ProofClientError::MalformedPayload(
+                "binary FRI job must carry exactly one prover input frame".to_string(),
+            ));
+        };
+        Ok(FriJobInputs {
+            batch_number: self.batch_number,
+            vk_hash: self.vk_hash,
+            prover_input,
+            expected_hash_u32s: self.expected_hash_u32s,
+            last_block_timestamp: self.last_block_timestamp,
+        })
+    }
+}
+
 impl TryInto<SnarkProofInputs> for GetSnarkProofPayload {
-    type Error = anyhow::Error;
+    type Error = ProofClientError;
 
     fn try_into(self) -> Result<SnarkProofInputs, Self::Error> {
-        let mut fri_proofs = vec![];
-        for encoded_proof in self.fri_proofs {
-            let (fri_proof, _) = bincode::serde::decode_from_slice(
-                &STANDARD.decode(encoded_proof)?,
-                bincode::config::standard(),
-            )?;
-            fri_proofs.push(fri_proof);
+        let mut decoder = FriProofDecoder::default();
+        for (i, encoded) in self.fri_proofs.into_iter().enumerate() {
+            decoder.push(STANDARD.decode(encoded).map_err(|e| {
+                ProofClientError::MalformedPayload(format!("FRI proof {i} is not base64: {e}"))
+            })?)?;
         }
+        let header = SnarkJobHeader {
+            from_batch_number: self.from_batch_number,
+            to_batch_number: self.to_batch_number,
+            vk_hash: self.vk_hash,
+        };
+        header.into_inputs(decoder.finish()?)
+    }
+}
 
+impl SnarkJobHeader {
+    /// Job inputs from the header and the job's decoded FRI proofs.
+    fn into_inputs(
+        self,
+        fri_proofs: Vec<UnrolledProgramProof>,
+    ) -> Result<SnarkProofInputs, ProofClientError> {
+        let batch_number = |name: &str, value: u64| {
+            value.try_into().map(L2BatchNumber).map_err(|_| {
+                ProofClientError::MalformedPayload(format!(
+                    "{name} {value} does not fit into a u32 batch number"
+                ))
+            })
+        };
         Ok(SnarkProofInputs {
-            from_batch_number: L2BatchNumber(
-                self.from_batch_number
-                    .try_into()
-                    .expect("from_batch_number should fit into L2BatchNumber(u32)"),
-            ),
-            to_batch_number: L2BatchNumber(
-                self.to_batch_number
-                    .try_into()
-                    .expect("to_batch_number should fit into L2BatchNumber(u32)"),
-            ),
+            from_batch_number: batch_number("from_batch_number", self.from_batch_number)?,
+            to_batch_number: batch_number("to_batch_number", self.to_batch_number)?,
             vk_hash: self.vk_hash,
             fri_proofs,
         })
     }
 }
 
+/// Serialize a SNARK proof into the base64-encoded form the sequencer expects.
+pub(crate) fn serialize_snark_proof(proof: &SnarkWrapperProof) -> String {
+    STANDARD.encode(snark_proof_bytes(proof))
+}
+
+/// Hex SHA-256 of a job input or proof, logged when it is received or submitted so it can be
+/// matched against the sequencer's records.
+pub fn payload_digest(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// `Idempotency-Key` of a proof submission: the hex SHA-256 of the batch range, the vk hash
+/// and the SHA-256 of the raw proof (not of its encoding, so JSON and binary submissions of
+/// a proof share a key). A sequencer seeing a key again answers `409` instead of storing
+/// the proof twice.
+pub fn submission_idempotency_key(
+    from_batch_number: u64,
+    to_batch_number: u64,
+    vk_hash: &str,
+    proof: &[u8],
+) -> String {
+    let proof_digest = Sha256::digest(proof);
+    let key = Sha256::new()
+        .chain_update(format!("{from_batch_number}-{to_batch_number}:{vk_hash}:"))
+        .chain_update(proof_digest)
+        .finalize();
+    format!("{key:x}")
+}
+
+/// The SNARK proof as the sequencer expects it, before base64: big-endian 32-byte words.
+pub fn snark_proof_bytes(proof: &SnarkWrapperProof) -> Vec<u8> {
+    let (_, serialized_proof) = crypto_codegen::serialize_proof(proof);
+
+    serialized_proof
+        .iter()
+        .flat_map(|chunk| {
+            let mut buf = [0u8; 32];
+            chunk.to_big_endian(&mut buf);
+            buf
+        })
+        .collect()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SnarkProofInputs {
     pub from_batch_number: L2BatchNumber,
@@ -110,6 +274,29 @@ pub struct FriJobInputs {
     pub batch_number: u32,
     pub vk_hash: String,
     pub prover_input: Vec<u8>,
+    /// Output hash the batch's proof must carry in registers 10..=17, if the sequencer
+    /// knows it up front.
+    pub expected_hash_u32s: Option<[u32; 8]>,
+    pub last_block_timestamp: Option<u64>,
+}
+
+/// Why a prover hands a picked job back to the sequencer instead of proving it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseReason {
+    /// The prover doesn't support the job's protocol version (vk hash).
+    UnsupportedVersion,
+    /// The job's protocol version or proofs are for a different app program than the
+    /// prover runs.
+    ProgramMismatch,
+    /// The job's inputs are malformed.
+    MalformedInput,
+    /// Proving the job failed.
+    ProvingFailed,
+    /// Released by an operator.
+    Operator,
+    /// The prover picked jobs from several sequencers at once and is proving another one.
+    Busy,
 }
 
 #[async_trait]
@@ -117,9 +304,25 @@ pub trait ProofClient: Send + Sync {
     /// Returns the sequencer URL for logging purposes.
     fn sequencer_url(&self) -> &Url;
 
+    /// How long picks wait on the sequencer for a job to become available, if it long-polls.
+    /// `None` means empty picks return right away, so callers should back off when idle.
+    fn long_poll_wait(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Fetch the sequencer's capabilities and adapt to them: the API version to talk and
+    /// the optional features to use. Returns `Ok(None)` if there is nothing to negotiate
+    /// (e.g. the sequencer predates capabilities); the client then assumes the first of
+    /// [`SUPPORTED_API_VERSIONS`] and probes optional features as it uses them.
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        Ok(None)
+    }
+
     /// Fetch the next FRI batch to prove.
     /// Returns `Ok(None)` if there's no batch pending (204 No Content).
-    async fn pick_fri_job(&self) -> anyhow::Result<Option<FriJobInputs>>;
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError>;
 
     /// Submit a FRI proof for the processed batch.
     async fn submit_fri_proof(
@@ -127,11 +330,18 @@ pub trait ProofClient: Send + Sync {
         batch_number: u32,
         vk_hash: String,
         proof: String,
-    ) -> anyhow::Result<()>;
+    ) -> Result<(), ProofClientError>;
+
+    /// Report a FRI proof whose output doesn't match the job's expected hash, instead of
+    /// submitting it as a proof.
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError>;
 
     /// Fetch the next SNARK job to prove.
     /// Returns `Ok(None)` if there's no job pending (204 No Content).
-    async fn pick_snark_job(&self) -> anyhow::Result<Option<SnarkProofInputs>>;
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError>;
 
     /// Submit a SNARK proof for the processed batch range.
     async fn submit_snark_proof(
@@ -140,25 +350,159 @@ pub trait ProofClient: Send + Sync {
         to_batch_number: L2BatchNumber,
         vk_hash: String,
         proof: SnarkWrapperProof,
-    ) -> anyhow::Result<()>;
+    ) -> Result<(), ProofClientError>;
+
+    /// Extend the lease of a picked FRI job, so it isn't reassigned while being proven.
+    /// Returns `Ok(false)` if the sequencer doesn't support lease extension.
+    async fn heartbeat_fri_job(&self, _batch_number: u32) -> Result<bool, ProofClientError> {
+        Ok(false)
+    }
+
+    /// Extend the lease of a picked SNARK job, so it isn't reassigned while being proven.
+    /// Returns `Ok(false)` if the sequencer doesn't support lease extension.
+    async fn heartbeat_snark_job(
+        &self,
+        _from_batch_number: L2BatchNumber,
+        _to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        Ok(false)
+    }
+
+    /// Hand a picked FRI job back, so the sequencer can reassign it right away instead of
+    /// waiting for its lease to expire. `message` is free-form detail for the sequencer's
+    /// logs. Returns `Ok(false)` if the sequencer doesn't support releasing jobs.
+    async fn release_fri_job(
+        &self,
+        _batch_number: u32,
+        _reason: ReleaseReason,
+        _message: String,
+    ) -> Result<bool, ProofClientError> {
+        Ok(false)
+    }
+
+    /// Hand a picked SNARK job back; see [`ProofClient::release_fri_job`].
+    async fn release_snark_job(
+        &self,
+        _from_batch_number: L2BatchNumber,
+        _to_batch_number: L2BatchNumber,
+        _reason: ReleaseReason,
+        _message: String,
+    ) -> Result<bool, ProofClientError> {
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl<T: ProofClient + ?Sized> ProofClient for Box<T> {
+    fn sequencer_url(&self) -> &Url {
+        (**self).sequencer_url()
+    }
+
+    fn long_poll_wait(&self) -> Option<Duration> {
+        (**self).long_poll_wait()
+    }
+
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        (**self).negotiate_capabilities().await
+    }
+
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+        (**self).pick_fri_job().await
+    }
+
+    async fn submit_fri_proof(
+        &self,
+        batch_number: u32,
+        vk_hash: String,
+        proof: String,
+    ) -> Result<(), ProofClientError> {
+        (**self)
+            .submit_fri_proof(batch_number, vk_hash, proof)
+            .await
+    }
+
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        (**self).submit_failed_fri_proof(payload).await
+    }
+
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+        (**self).pick_snark_job().await
+    }
+
+    async fn submit_snark_proof(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
+        proof: SnarkWrapperProof,
+    ) -> Result<(), ProofClientError> {
+        (**self)
+            .submit_snark_proof(from_batch_number, to_batch_number, vk_hash, proof)
+            .await
+    }
+
+    async fn heartbeat_fri_job(&self, batch_number: u32) -> Result<bool, ProofClientError> {
+        (**self).heartbeat_fri_job(batch_number).await
+    }
+
+    async fn heartbeat_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        (**self)
+            .heartbeat_snark_job(from_batch_number, to_batch_number)
+            .await
+    }
+
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        (**self)
+            .release_fri_job(batch_number, reason, message)
+            .await
+    }
+
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        (**self)
+            .release_snark_job(from_batch_number, to_batch_number, reason, message)
+            .await
+    }
 }
 
 #[async_trait]
 pub trait PeekableProofClient {
     /// Peek at a FRI job by batch number.
     /// Note: you can only peek failed jobs as successful ones are removed.
-    async fn peek_fri_job(&self, batch_number: u32) -> anyhow::Result<Option<(u32, Vec<u8>)>>;
+    async fn peek_fri_job(
+        &self,
+        batch_number: u32,
+    ) -> Result<Option<FriJobInputs>, ProofClientError>;
 
     /// Peek at a SNARK job by batch range.
     async fn peek_snark_job(
         &self,
         from_batch_number: u32,
         to_batch_number: u32,
-    ) -> anyhow::Result<Option<SnarkProofInputs>>;
+    ) -> Result<Option<SnarkProofInputs>, ProofClientError>;
 
     /// Get a failed FRI proof by batch number.
     async fn get_failed_fri_proof(
         &self,
         batch_number: u32,
-    ) -> anyhow::Result<Option<FailedFriProofPayload>>;
+    ) -> Result<Option<FailedFriProofPayload>, ProofClientError>;
 }
@@ -1,9 +1,14 @@
-use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::path::Path;
+
+use anyhow::{anyhow, Context as _, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use clap::{Parser, Subcommand};
-use tracing_subscriber::{fmt, EnvFilter};
-use zkos_wrapper::SnarkWrapperProof;
 use zksync_sequencer_proof_client::{
-    FriJobInputs, L2BatchNumber, ProofClient, SequencerEndpoint, SequencerProofClient,
+    init_tracing, payload_digest, AuthArgs, EnvelopeFormat, Enveloped, FailedFriProofPayload,
+    FriJobInputs, FriProofFile, L2BatchNumber, PeekableProofClient, ProofClient, ReleaseReason,
+    SequencerEndpoint, SequencerProofClient, SnarkProofFile, SnarkProofInputs, TelemetryArgs,
+    TelemetryGuard,
 };
 
 #[derive(Parser)]
@@ -11,7 +16,11 @@ use zksync_sequencer_proof_client::{
 struct Cli {
     /// Sequencer URL to submit proofs to
     ///
-    /// Format: http[s]://[username:password@]host:port
+    /// Format: http[s]://[username:password@]host:port[#auth options]
+    ///
+    /// Credentials can also come from `#basic_file=<path>`, `#basic_env=<VAR>` or
+    /// `#bearer_file=<path>`; `#tls_cert=<path>&tls_key=<path>` and `#tls_ca=<path>`
+    /// configure mutual TLS.
     #[arg(
         short,
         long,
@@ -21,6 +30,17 @@ struct Cli {
     )]
     url: Option<SequencerEndpoint>,
 
+    #[command(flatten)]
+    auth: AuthArgs,
+
+    #[command(flatten)]
+    telemetry: TelemetryArgs,
+
+    /// Format of the job and proof files written: `json`, `bin` or `b64`
+    /// [default: by the file's extension, JSON otherwise]. Files are read in any format.
+    #[arg(long, global = true, value_enum)]
+    format: Option<EnvelopeFormat>,
+
     /// Activate verbose logging (`-v`, `-vv`, ...)
     #[arg(short, long, global = true, action = clap::ArgAction::Count)]
     verbose: u8,
@@ -31,13 +51,18 @@ struct Cli {
 
 impl Cli {
     /// Regular `::parse()`, but checks that the `--url` argument is provided & initializes tracing.
-    fn init() -> Result<Self> {
+    fn init() -> Result<(Self, TelemetryGuard)> {
         let cli = Cli::parse();
         if cli.url.is_none() {
             return Err(anyhow!("The --url <URL> argument is required. It can be placed anywhere on the command line."));
         }
-        init_tracing(cli.verbose);
-        Ok(cli)
+        let level = match cli.verbose {
+            0 => "info",
+            1 => "debug",
+            _ => "trace",
+        };
+        let telemetry = init_tracing("zksync_sequencer_proof_client", level, &cli.telemetry)?;
+        Ok((cli, telemetry))
     }
 
     /// Return sequencer client from CLI params. To be called only after `Cli::init()`.
@@ -46,7 +71,8 @@ impl Cli {
         SequencerProofClient::new(
             self.url
                 .clone()
-                .expect("called sequencer_client() before init()"),
+                .expect("called sequencer_client() before init()")
+                .allow_insecure_credentials(self.auth.allow_insecure_credentials),
             "cli_client".to_string(),
             None,
             vec![],
@@ -56,6 +82,8 @@ impl Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Prints the prover API version, active vk hashes and optional features the sequencer advertises
+    Info,
     /// Picks the next FRI proof job from the sequencer; sequencer marks job as picked (and will not give it to other clients, until the job expires)
     PickFri {
         /// Path to the FRI proof job to save
@@ -64,12 +92,12 @@ enum Commands {
     },
     /// Submits batch's FRI proof to sequencer
     SubmitFri {
-        /// The batch number to submit the FRI proof for
+        /// The batch number to submit the FRI proof for; checked against the proof file
         #[arg(short, long, value_name = "BATCH_NUMBER")]
-        batch_number: u32,
-        /// VK hash of the proof chain to be submitted
-        #[arg(short, long, value_name = "VK_HASH")]
-        vk_hash: String,
+        batch_number: Option<u32>,
+        /// VK hash of the proof chain to be submitted; checked against the proof file
+        #[arg(long, value_name = "VK_HASH")]
+        vk_hash: Option<String>,
         /// Path to the FRI proof file to submit
         #[arg(
             short,
@@ -79,6 +107,17 @@ enum Commands {
         )]
         path: String,
     },
+    /// Reports a FRI proof whose output doesn't match its batch's expected hash to the sequencer
+    SubmitFailedFri {
+        /// Path to the `FailedFriProofPayload` JSON file to submit
+        #[arg(
+            short,
+            long,
+            value_name = "FAILED_FRI_PATH",
+            default_value = "./failed_fri_proof.json"
+        )]
+        path: String,
+    },
     /// Picks the next SNARK proof job from the sequencer; sequencer marks job as picked (and will not give it to other clients, until the job expires)
     PickSnark {
         /// Path to the SNARK proof job to save
@@ -92,15 +131,17 @@ enum Commands {
     },
     /// Submits batch's SNARK proof to sequencer
     SubmitSnark {
-        /// The SNARK aggregates proofs starting from this batch number
+        /// The SNARK aggregates proofs starting from this batch number; checked against the
+        /// proof file
         #[arg(short, long, value_name = "FROM_BATCH")]
-        from_batch_number: u32,
-        /// The SNARK aggregates proofs up to this batch number (inclusive)
+        from_batch_number: Option<u32>,
+        /// The SNARK aggregates proofs up to this batch number (inclusive); checked against
+        /// the proof file
         #[arg(short, long, value_name = "TO_BATCH")]
-        to_batch_number: u32,
-        /// VK hash of the proof chain to be submitted
-        #[arg(short, long, value_name = "VK_HASH")]
-        vk_hash: String,
+        to_batch_number: Option<u32>,
+        /// VK hash of the proof chain to be submitted; checked against the proof file
+        #[arg(long, value_name = "VK_HASH")]
+        vk_hash: Option<String>,
         /// Path to the SNARK proof file to submit
         #[arg(
             short,
@@ -110,42 +151,114 @@ enum Commands {
         )]
         path: String,
     },
+    /// Peeks at the FRI job of a batch without picking it, and saves it in the `pick-fri` format
+    PeekFri {
+        /// The batch number of the FRI job
+        #[arg(short, long, value_name = "BATCH_NUMBER")]
+        batch_number: u32,
+        /// Path to the FRI proof job to save
+        #[arg(short, long, value_name = "FRI_PATH", default_value = "./fri_job.json")]
+        path: String,
+    },
+    /// Peeks at a SNARK job without picking it, and saves it in the `pick-snark` format
+    PeekSnark {
+        /// First batch number of the SNARK job
+        #[arg(short, long, value_name = "FROM_BATCH")]
+        from_batch_number: u32,
+        /// Last batch number of the SNARK job (inclusive)
+        #[arg(short, long, value_name = "TO_BATCH")]
+        to_batch_number: u32,
+        /// Path to the SNARK proof job to save
+        #[arg(
+            short,
+            long,
+            value_name = "SNARK_PATH",
+            default_value = "./snark_job.json"
+        )]
+        path: String,
+    },
+    /// Downloads the failed FRI proof reported for a batch, and saves it in the `submit-failed-fri` format
+    GetFailedFri {
+        /// The batch number of the failed FRI proof
+        #[arg(short, long, value_name = "BATCH_NUMBER")]
+        batch_number: u32,
+        /// Path to the `FailedFriProofPayload` JSON file to save
+        #[arg(
+            short,
+            long,
+            value_name = "FAILED_FRI_PATH",
+            default_value = "./failed_fri_proof.json"
+        )]
+        path: String,
+        /// Also save the proof itself, in the `submit-fri` format
+        #[arg(long, value_name = "FRI_PATH")]
+        proof_path: Option<String>,
+    },
+    /// Hands a picked job back to the sequencer, so it is reassigned without waiting for its lease to expire
+    Release {
+        #[command(subcommand)]
+        job: ReleaseJob,
+        /// Reason reported to the sequencer
+        #[arg(short, long, value_enum, global = true, default_value_t = ReleaseReason::Operator)]
+        reason: ReleaseReason,
+        /// Free-form detail reported to the sequencer
+        #[arg(short, long, global = true, default_value = "released by operator")]
+        message: String,
+    },
 }
 
-fn init_tracing(verbosity: u8) {
-    let level = match verbosity {
-        0 => "info",
-        1 => "debug",
-        _ => "trace",
-    };
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
-    fmt::Subscriber::builder()
-        .with_env_filter(env_filter)
-        .init();
+#[derive(Subcommand)]
+enum ReleaseJob {
+    /// Releases the picked FRI job of a batch
+    Fri {
+        /// The batch number of the FRI job
+        #[arg(short, long, value_name = "BATCH_NUMBER")]
+        batch_number: u32,
+    },
+    /// Releases a picked SNARK job
+    Snark {
+        /// First batch number of the SNARK job
+        #[arg(short, long, value_name = "FROM_BATCH")]
+        from_batch_number: u32,
+        /// Last batch number of the SNARK job (inclusive)
+        #[arg(short, long, value_name = "TO_BATCH")]
+        to_batch_number: u32,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::init()?;
+    let (cli, telemetry) = Cli::init()?;
+    let result = run(cli).await;
+    telemetry.shutdown().await;
+    result
+}
 
+async fn run(cli: Cli) -> Result<()> {
     let client = cli.sequencer_client()?;
 
     let url = client.sequencer_url();
+    let format = cli.format;
 
     match cli.command {
+        Commands::Info => match client.negotiate_capabilities().await? {
+            Some(capabilities) => {
+                println!("{}", serde_json::to_string_pretty(&capabilities)?);
+            }
+            None => {
+                tracing::info!("Sequencer at {url} does not advertise its capabilities.");
+            }
+        },
         Commands::PickFri { path } => {
             tracing::info!("Picking next FRI proof job from sequencer at {}", url);
             match client.pick_fri_job().await? {
-                Some(FriJobInputs {
-                    batch_number,
-                    vk_hash,
-                    prover_input,
-                }) => {
+                Some(job) => {
+                    job.write_to(Path::new(&path), format)?;
                     tracing::info!(
-                        "Picked FRI job for batch {batch_number} with vk {vk_hash}, saved job to path {path}"
+                        "Picked FRI job for batch {} with vk {}, saved job to path {path}",
+                        job.batch_number,
+                        job.vk_hash
                     );
-                    let mut dst = std::fs::File::create(path).unwrap();
-                    serde_json::to_writer_pretty(&mut dst, &prover_input).unwrap();
                 }
                 None => {
                     tracing::info!("No FRI proof jobs available at the moment.");
@@ -157,17 +270,32 @@ async fn main() -> Result<()> {
             vk_hash,
             path,
         } => {
+            let proof = FriProofFile::read_from(Path::new(&path))?;
+            check_matches("batch number", batch_number, proof.batch_number)?;
+            check_matches("vk hash", vk_hash, proof.vk_hash.clone())?;
+            let batch_number = proof.batch_number;
             tracing::info!("Submitting FRI proof for batch {batch_number} with proof from {path} to sequencer at {}", url);
-            let file = std::fs::File::open(path)?;
-            let fri_proof: String = serde_json::from_reader(file)?;
             client
-                .submit_fri_proof(batch_number, vk_hash, fri_proof)
+                .submit_fri_proof(batch_number, proof.vk_hash.clone(), proof.proof_base64())
                 .await?;
             tracing::info!(
                 "Submitted FRI proof for batch {batch_number} to sequencer at {}",
                 url
             );
         }
+        Commands::SubmitFailedFri { path } => {
+            tracing::info!(
+                "Submitting failed FRI proof from {path} to sequencer at {}",
+                url
+            );
+            let payload = FailedFriProofPayload::read_from(Path::new(&path))?;
+            let batch_number = payload.batch_number;
+            client.submit_failed_fri_proof(payload).await?;
+            tracing::info!(
+                "Submitted failed FRI proof for batch {batch_number} to sequencer at {}",
+                url
+            );
+        }
         Commands::PickSnark { path } => {
             tracing::info!("Picking next SNARK proof job from sequencer at {}", url);
             match client.pick_snark_job().await? {
@@ -177,8 +305,7 @@ async fn main() -> Result<()> {
                         snark_proof_inputs.from_batch_number,
                         snark_proof_inputs.to_batch_number
                     );
-                    let mut dst = std::fs::File::create(&path).unwrap();
-                    serde_json::to_writer_pretty(&mut dst, &snark_proof_inputs).unwrap();
+                    snark_proof_inputs.write_to(Path::new(&path), format)?;
                     tracing::info!(
                         "Saved SNARK job for batches [{}, {}] with vk {} to path {path}",
                         snark_proof_inputs.from_batch_number,
@@ -197,20 +324,190 @@ async fn main() -> Result<()> {
             vk_hash,
             path,
         } => {
+            let proof = SnarkProofFile::read_from(Path::new(&path))?;
+            check_matches("from batch number", from_batch_number, proof.from_batch_number.0)?;
+            check_matches("to batch number", to_batch_number, proof.to_batch_number.0)?;
+            check_matches("vk hash", vk_hash, proof.vk_hash.clone())?;
+            let (from_batch_number, to_batch_number) = (proof.from_batch_number, proof.to_batch_number);
             tracing::info!("Submitting SNARK proof for batches [{from_batch_number}, {to_batch_number}] with proof from {path} to sequencer at {}", url);
-            let file = std::fs::File::open(path)?;
-            let snark_wrapper: SnarkWrapperProof = serde_json::from_reader(file)?;
             client
-                .submit_snark_proof(
-                    L2BatchNumber(from_batch_number),
-                    L2BatchNumber(to_batch_number),
-                    vk_hash,
-                    snark_wrapper,
-                )
+                .submit_snark_proof(from_batch_number, to_batch_number, proof.vk_hash, proof.proof)
                 .await?;
             tracing::info!("Submitted proof for batches [{from_batch_number}, {to_batch_number}] to sequencer at {}", url);
         }
+        Commands::PeekFri { batch_number, path } => {
+            match client.peek_fri_job(batch_number).await? {
+                Some(job) => {
+                    job.write_to(Path::new(&path), format)?;
+                    print_fri_job(&job);
+                    println!("Saved FRI job to {path}");
+                }
+                None => println!("Sequencer at {url} has no FRI job for batch {batch_number}."),
+            }
+        }
+        Commands::PeekSnark {
+            from_batch_number,
+            to_batch_number,
+            path,
+        } => match client
+            .peek_snark_job(from_batch_number, to_batch_number)
+            .await?
+        {
+            Some(job) => {
+                job.write_to(Path::new(&path), format)?;
+                print_snark_job(&job);
+                println!("Saved SNARK job to {path}");
+            }
+            None => println!(
+                "Sequencer at {url} has no SNARK job for batches [{from_batch_number}, {to_batch_number}]."
+            ),
+        },
+        Commands::GetFailedFri {
+            batch_number,
+            path,
+            proof_path,
+        } => match client.get_failed_fri_proof(batch_number).await? {
+            Some(payload) => {
+                payload.write_to(Path::new(&path), format)?;
+                print_failed_fri_proof(&payload)?;
+                println!("Saved failed FRI proof to {path}");
+                if let Some(proof_path) = proof_path {
+                    let proof = FriProofFile {
+                        batch_number: u32::try_from(payload.batch_number)?,
+                        vk_hash: payload.vk_hash.clone(),
+                        proof: STANDARD.decode(&payload.proof)?,
+                    };
+                    proof.write_to(Path::new(&proof_path), format)?;
+                    println!("Saved FRI proof to {proof_path}");
+                }
+            }
+            None => println!(
+                "Sequencer at {url} has no failed FRI proof for batch {batch_number}."
+            ),
+        },
+        Commands::Release {
+            job,
+            reason,
+            message,
+        } => {
+            let (job, released) = match job {
+                ReleaseJob::Fri { batch_number } => (
+                    format!("FRI job for batch {batch_number}"),
+                    client
+                        .release_fri_job(batch_number, reason, message)
+                        .await?,
+                ),
+                ReleaseJob::Snark {
+                    from_batch_number,
+                    to_batch_number,
+                } => (
+                    format!("SNARK job for batches [{from_batch_number}, {to_batch_number}]"),
+                    client
+                        .release_snark_job(
+                            L2BatchNumber(from_batch_number),
+                            L2BatchNumber(to_batch_number),
+                            reason,
+                            message,
+                        )
+                        .await?,
+                ),
+            };
+            if !released {
+                return Err(anyhow!(
+                    "Sequencer at {url} does not support releasing jobs"
+                ));
+            }
+            tracing::info!("Released {job} ({reason:?}) on sequencer at {}", url);
+        }
+    }
+
+    Ok(())
+}
+
+/// Fail if a value given on the command line differs from the one in a file.
+fn check_matches<T: PartialEq + Display>(what: &str, given: Option<T>, in_file: T) -> Result<()> {
+    match given {
+        Some(given) if given != in_file => Err(anyhow!(
+            "The {what} {given} does not match the file's {in_file}"
+        )),
+        _ => Ok(()),
     }
+}
+
+fn print_fri_job(job: &FriJobInputs) {
+    println!("FRI job for batch {}", job.batch_number);
+    println!("  vk hash:              {}", job.vk_hash);
+    println!(
+        "  prover input:         {} bytes, sha256 {}",
+        job.prover_input.len(),
+        payload_digest(&job.prover_input)
+    );
+    match job.expected_hash_u32s {
+        Some(expected) => println!("  expected hash:        {}", hex_words(&expected)),
+        None => println!("  expected hash:        unknown"),
+    }
+    if let Some(timestamp) = job.last_block_timestamp {
+        println!("  last block timestamp: {timestamp}");
+    }
+}
 
+fn print_snark_job(job: &SnarkProofInputs) {
+    println!(
+        "SNARK job for batches [{}, {}]",
+        job.from_batch_number, job.to_batch_number
+    );
+    println!("  vk hash:    {}", job.vk_hash);
+    println!("  FRI proofs: {}", job.fri_proofs.len());
+}
+
+fn print_failed_fri_proof(payload: &FailedFriProofPayload) -> Result<()> {
+    let proof = STANDARD
+        .decode(&payload.proof)
+        .context("Failed FRI proof is not base64")?;
+    println!("Failed FRI proof for batch {}", payload.batch_number);
+    println!("  vk hash:              {}", payload.vk_hash);
+    if let Some(timestamp) = payload.last_block_timestamp {
+        println!("  last block timestamp: {timestamp}");
+    }
+    println!(
+        "  proof:                {} bytes, sha256 {}",
+        proof.len(),
+        payload_digest(&proof)
+    );
+    // Registers 10..=17 carry the batch output hash, 18..=25 the app program commitment.
+    let (output_hash, program) = payload.proof_final_register_values.split_at(8);
+    let mismatches = payload
+        .expected_hash_u32s
+        .iter()
+        .zip(output_hash)
+        .filter(|(expected, actual)| expected != actual)
+        .count();
+    println!("  output hash mismatch: {mismatches} of 8 words");
+    println!("    register  expected    actual");
+    for (i, (expected, actual)) in payload
+        .expected_hash_u32s
+        .iter()
+        .zip(output_hash)
+        .enumerate()
+    {
+        let marker = if expected == actual {
+            ""
+        } else {
+            "  <- differs"
+        };
+        println!(
+            "    {:<8}  {expected:#010x}  {actual:#010x}{marker}",
+            10 + i
+        );
+    }
+    println!("  program commitment:   {}", hex_words(program));
     Ok(())
 }
+
+fn hex_words(words: &[u32]) -> String {
+    words
+        .iter()
+        .map(|word| format!("{word:08x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,14 +1,36 @@
-use vise::{EncodeLabelSet, EncodeLabelValue, Family, Histogram, Metrics};
+use vise::{Counter, EncodeLabelSet, EncodeLabelValue, Family, Gauge, Histogram, Metrics};
 
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, EncodeLabelValue, EncodeLabelSet,
 )]
 #[metrics(label = "type", rename_all = "snake_case")]
 pub(crate) enum Method {
+    Info,
     PickFri,
     SubmitFri,
+    SubmitFailedFri,
     PickSnark,
     SubmitSnark,
+    PeekSnark,
+}
+
+/// Whether a payload size is measured before or after `Content-Encoding` compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(rename_all = "snake_case")]
+pub(crate) enum PayloadStage {
+    Decoded,
+    Wire,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct PayloadLabels {
+    pub method: Method,
+    pub stage: PayloadStage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct EndpointLabels {
+    pub url: String,
 }
 
 #[derive(Debug, Clone, Metrics)]
@@ -16,8 +38,25 @@ pub(crate) enum Method {
 pub struct SequencerClientMetrics {
     #[metrics(buckets = vise::Buckets::exponential(0.001..=2.0, 2.0), unit = vise::Unit::Seconds)]
     pub time_taken: Family<Method, Histogram>,
+    /// Sizes of job and proof bodies exchanged with sequencers
+    #[metrics(buckets = vise::Buckets::exponential(1024.0..=4_294_967_296.0, 4.0), unit = vise::Unit::Bytes)]
+    pub payload_size: Family<PayloadLabels, Histogram<usize>>,
+    /// Submissions the sequencer already had the proof for (`409`), counted as accepted
+    pub duplicate_submissions: Family<Method, Counter>,
+    /// Retries issued by `RetryingProofClient`
+    pub retries: Family<Method, Counter>,
+    /// Circuit breaker state of every pooled sequencer: 0 - closed (healthy),
+    /// 1 - half-open (probing), 2 - open (skipped)
+    pub endpoint_state: Family<EndpointLabels, Gauge<u64>>,
 }
 
 #[vise::register]
 pub(crate) static SEQUENCER_CLIENT_METRICS: vise::Global<SequencerClientMetrics> =
     vise::Global::new();
+
+/// Record the size of a job or proof body before and after compression.
+pub(crate) fn observe_payload_size(method: Method, decoded: usize, wire: usize) {
+    for (stage, size) in [(PayloadStage::Decoded, decoded), (PayloadStage::Wire, wire)] {
+        SEQUENCER_CLIENT_METRICS.payload_size[&PayloadLabels { method, stage }].observe(size);
+    }
+}
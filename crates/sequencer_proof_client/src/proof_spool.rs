@@ -0,0 +1,517 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zkos_wrapper::SnarkWrapperProof;
+
+use crate::{L2BatchNumber, ProofClient, ProofClientError};
+
+/// A proof waiting to be acknowledged by the sequencer it was proven for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpooledProof {
+    Fri {
+        batch_number: u32,
+        vk_hash: String,
+        /// Base64-encoded proof, as submitted.
+        proof: String,
+    },
+    Snark {
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
+        proof: SnarkWrapperProof,
+    },
+}
+
+impl SpooledProof {
+    fn file_stem(&self) -> String {
+        match self {
+            Self::Fri { batch_number, .. } => format!("fri_{batch_number}"),
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+                ..
+            } => format!("snark_{from_batch_number}_{to_batch_number}"),
+        }
+    }
+
+    async fn submit(self, client: &dyn ProofClient) -> Result<(), ProofClientError> {
+        match self {
+            Self::Fri {
+                batch_number,
+                vk_hash,
+                proof,
+            } => client.submit_fri_proof(batch_number, vk_hash, proof).await,
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+                vk_hash,
+                proof,
+            } => {
+                client
+                    .submit_snark_proof(from_batch_number, to_batch_number, vk_hash, proof)
+                    .await
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SpooledProof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fri {
+                batch_number,
+                vk_hash,
+                ..
+            } => write!(f, "FRI proof for batch {batch_number} with vk hash {vk_hash}"),
+            Self::Snark {
+                from_batch_number,
+                to_batch_number,
+                vk_hash,
+                ..
+            } => write!(
+                f,
+                "SNARK proof for batches {from_batch_number} to {to_batch_number} with vk hash {vk_hash}"
+            ),
+        }
+    }
+}
+
+/// On-disk form of a spooled proof.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub sequencer_url: Url,
+    pub proof: SpooledProof,
+}
+
+/// Handle of a spooled proof being submitted; hand it back to [`ProofSpool::settle`].
+#[derive(Debug)]
+#[must_use = "an unsettled ticket keeps the proof from being re-submitted"]
+pub struct SpoolTicket {
+    path: PathBuf,
+}
+
+/// Write-ahead spool for proofs, so a sequencer outage or a restart doesn't cost proving
+/// work.
+///
+/// Every proof is written to the spool directory (one JSON file per proof and sequencer)
+/// before it is submitted and removed once the sequencer acknowledges it. Proofs whose
+/// submission failed stay in the spool and are re-submitted by
+/// [`ProofSpool::resubmit_pending`], which the provers run on startup and periodically
+/// afterwards. Entries being submitted by the prover loop are skipped by re-submission.
+#[derive(Debug)]
+pub struct ProofSpool {
+    dir: PathBuf,
+    in_flight: Mutex<HashSet<PathBuf>>,
+}
+
+impl ProofSpool {
+    /// Open the spool at `dir`, creating the directory if needed.
+    pub fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create spool directory {dir:?}"))?;
+        Ok(Self {
+            dir,
+            in_flight: Mutex::default(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Persist `proof`, to be submitted to `sequencer_url`, and claim it for the caller's
+    /// own submission. The entry is claimed before it is written, so the re-submitter never
+    /// picks it up in between, and written and synced off the runtime.
+    ///
+    /// # Errors
+    /// * if the same proof is being re-submitted from the spool right now
+    /// * if the entry cannot be written
+    pub async fn put(
+        &self,
+        sequencer_url: &Url,
+        proof: SpooledProof,
+    ) -> anyhow::Result<SpoolTicket> {
+        let path = self.dir.join(format!(
+            "{}_{:016x}.json",
+            proof.file_stem(),
+            url_hash(sequencer_url)
+        ));
+        if !self.lock_in_flight().insert(path.clone()) {
+            anyhow::bail!("Spooled proof {path:?} is being re-submitted");
+        }
+        let ticket = SpoolTicket { path };
+
+        let entry = SpoolEntry {
+            sequencer_url: sequencer_url.clone(),
+            proof,
+        };
+        let (dir, path) = (self.dir.clone(), ticket.path.clone());
+        match blocking(move || write_entry(&dir, &path, &entry)).await {
+            Ok(()) => Ok(ticket),
+            Err(e) => {
+                self.release(ticket);
+                Err(e)
+            }
+        }
+    }
+
+    /// Record the outcome of submitting a spooled proof. The entry is removed once the
+    /// sequencer acknowledged it or rejected it for good (e.g. the job was proven
+    /// elsewhere); otherwise it stays for re-submission.
+    pub async fn settle(&self, ticket: SpoolTicket, result: &Result<(), ProofClientError>) {
+        let done = match result {
+            Ok(()) => true,
+            // Credentials may be fixed by a restart; keep the proof until then.
+            Err(ProofClientError::Unauthorized(_)) => false,
+            Err(err) => !err.is_retryable(),
+        };
+        if done {
+            let path = ticket.path.clone();
+            let removed = blocking(move || {
+                std::fs::remove_file(&path).with_context(|| format!("Failed to remove {path:?}"))
+            })
+            .await;
+            if let Err(e) = removed {
+                tracing::warn!("Failed to remove spooled proof {:?}: {e:#}", ticket.path);
+            }
+        } else {
+            tracing::info!("Keeping spooled proof {:?} for re-submission", ticket.path);
+        }
+        self.release(ticket);
+    }
+
+    /// Give up the claim on an entry, leaving it for re-submission.
+    fn release(&self, ticket: SpoolTicket) {
+        self.lock_in_flight().remove(&ticket.path);
+    }
+
+    /// Spooled entries not currently being submitted, claimed for the caller. Only lists
+    /// the spool, off the runtime; the entries are read by [`Self::load`].
+    async fn claim_pending(&self) -> anyhow::Result<Vec<SpoolTicket>> {
+        let dir = self.dir.clone();
+        let paths = blocking(move || list_entries(&dir)).await?;
+
+        let mut in_flight = self.lock_in_flight();
+        Ok(paths
+            .into_iter()
+            .filter(|path| in_flight.insert(path.clone()))
+            .map(|path| SpoolTicket { path })
+            .collect())
+    }
+
+    /// Read a claimed entry off the runtime. An unreadable one (e.g. truncated by a crash of
+    /// an older version) is moved aside to `<name>.json.unreadable`, so it isn't retried
+    /// on every pass, and its claim is given up.
+    async fn load(&self, ticket: SpoolTicket) -> Option<(SpoolTicket, SpoolEntry)> {
+        let path = ticket.path.clone();
+        let e = match blocking(move || read_entry(&path)).await {
+            Ok(entry) => return Some((ticket, entry)),
+            Err(e) => e,
+        };
+        let aside = ticket.path.with_extension("json.unreadable");
+        tracing::error!(
+            "Moving unreadable spooled proof {:?} to {aside:?}: {e:#}",
+            ticket.path
+        );
+        let path = ticket.path.clone();
+        let moved = blocking(move || {
+            std::fs::rename(&path, &aside).with_context(|| format!("Failed to rename {path:?}"))
+        })
+        .await;
+        if let Err(e) = moved {
+            tracing::warn!("Failed to move {:?} aside: {e:#}", ticket.path);
+        }
+        self.release(ticket);
+        None
+    }
+
+    /// Submit every spooled proof not currently in flight to the client serving its
+    /// sequencer URL. Returns the number of proofs the sequencers acknowledged.
+    pub async fn resubmit_pending(&self, clients: &[Box<dyn ProofClient + Send + Sync>]) -> usize {
+        let pending = match self.claim_pending().await {
+            Ok(pending) => pending,
+            Err(e) => {
+                tracing::error!("Failed to read proof spool: {e:#}");
+                return 0;
+            }
+        };
+
+        let mut acknowledged = 0;
+        for ticket in pending {
+            let Some((ticket, entry)) = self.load(ticket).await else {
+                continue;
+            };
+            let Some(client) = clients
+                .iter()
+                .find(|client| client.sequencer_url() == &entry.sequencer_url)
+            else {
+                tracing::warn!(
+                    "Spooled {} is for sequencer {}, which is not configured; keeping it",
+                    entry.proof,
+                    entry.sequencer_url
+                );
+                self.release(ticket);
+                continue;
+            };
+
+            let description = entry.proof.to_string();
+            let result = entry.proof.submit(client.as_ref()).await;
+            match &result {
+                Ok(()) => {
+                    tracing::info!(
+                        "Re-submitted spooled {description} to sequencer {}",
+                        entry.sequencer_url
+                    );
+                    acknowledged += 1;
+                }
+                Err(e) if e.is_retryable() => tracing::warn!(
+                    "Failed to re-submit spooled {description} to sequencer {}: {e}; will retry",
+                    entry.sequencer_url
+                ),
+                Err(e) => tracing::warn!(
+                    "Sequencer {} rejected spooled {description}: {e}",
+                    entry.sequencer_url
+                ),
+            }
+            self.settle(ticket, &result).await;
+        }
+        acknowledged
+    }
+
+    /// Re-submit spooled proofs every `interval`, forever. Meant to be spawned next to the
+    /// prover loop.
+    pub async fn run_resubmitter(
+        self: Arc<Self>,
+        clients: Arc<Vec<Box<dyn ProofClient + Send + Sync>>>,
+        interval: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.resubmit_pending(&clients).await;
+        }
+    }
+
+    fn lock_in_flight(&self) -> std::sync::MutexGuard<'_, HashSet<PathBuf>> {
+        self.in_flight.lock().expect("proof spool state poisoned")
+    }
+}
+
+/// Command-line options enabling the [`ProofSpool`], shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct SpoolArgs {
+    /// Directory to keep proofs in until the sequencer acknowledges them. Proofs that fail to
+    /// submit are re-submitted periodically and on the next start.
+    #[arg(long, value_name = "DIR")]
+    pub spool_dir: Option<PathBuf>,
+    /// Interval between re-submissions of spooled proofs, in seconds
+    #[arg(long, default_value_t = 30)]
+    pub spool_resubmit_interval_secs: u64,
+}
+
+impl SpoolArgs {
+    /// Open the spool if `--spool-dir` is set, replay the proofs left in it and spawn the
+    /// periodic re-submission task.
+    pub async fn start(
+        &self,
+        clients: &Arc<Vec<Box<dyn ProofClient + Send + Sync>>>,
+    ) -> anyhow::Result<Option<Arc<ProofSpool>>> {
+        let Some(dir) = &self.spool_dir else {
+            return Ok(None);
+        };
+        let spool = Arc::new(ProofSpool::open(dir.clone())?);
+        let replayed = spool.resubmit_pending(clients).await;
+        tracing::info!("Using proof spool at {dir:?}, replayed {replayed} spooled proofs");
+
+        tokio::spawn(spool.clone().run_resubmitter(
+            clients.clone(),
+            Duration::from_secs(self.spool_resubmit_interval_secs),
+        ));
+        Ok(Some(spool))
+    }
+}
+
+/// Write `entry` to `path` in the spool directory `dir`.
+///
+/// Write-ahead: the entry must be durable before the proof is submitted, and a failed write
+/// must not leave a truncated entry in place.
+fn write_entry(dir: &Path, path: &Path, entry: &SpoolEntry) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let file = std::fs::File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {tmp_path:?}"))?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer(&mut writer, entry)
+        .with_context(|| format!("Failed to write {tmp_path:?}"))?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .with_context(|| format!("Failed to write {tmp_path:?}"))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {path:?} into place"))?;
+    std::fs::File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync spool directory {dir:?}"))
+}
+
+/// Spool entries in `dir`, in name order.
+fn list_entries(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list spool directory {dir:?}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_entry(path: &Path) -> anyhow::Result<SpoolEntry> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Run spool file system work `f` on a blocking thread.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(anyhow::anyhow!("spool task failed: {e}")))
+}
+
+/// FNV-1a of the URL: a short file-name component that is stable across builds.
+fn url_hash(url: &Url) -> u64 {
+    url.as_str()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn fri_proof(batch_number: u32) -> SpooledProof {
+        SpooledProof::Fri {
+            batch_number,
+            vk_hash: "0xaa".to_string(),
+            proof: "cHJvb2Y=".to_string(),
+        }
+    }
+
+    fn spooled_files(spool: &ProofSpool) -> usize {
+        std::fs::read_dir(spool.dir()).unwrap().count()
+    }
+
+    #[tokio::test]
+    async fn test_settle_keeps_only_retryable_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = ProofSpool::open(dir.path().to_path_buf()).unwrap();
+        let url = Url::parse("http://localhost:3124").unwrap();
+
+        let ticket = spool.put(&url, fri_proof(1)).await.unwrap();
+        spool.settle(ticket, &Ok(())).await;
+        assert_eq!(spooled_files(&spool), 0);
+
+        let ticket = spool.put(&url, fri_proof(2)).await.unwrap();
+        spool
+            .settle(ticket, &Err(ProofClientError::AlreadyProven))
+            .await;
+        assert_eq!(spooled_files(&spool), 0);
+
+        let ticket = spool.put(&url, fri_proof(3)).await.unwrap();
+        spool
+            .settle(
+                ticket,
+                &Err(ProofClientError::ServerError(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )),
+            )
+            .await;
+        assert_eq!(spooled_files(&spool), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_entries_are_not_claimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = ProofSpool::open(dir.path().to_path_buf()).unwrap();
+        let url = Url::parse("http://localhost:3124").unwrap();
+        let other_url = Url::parse("http://localhost:3125").unwrap();
+
+        let in_flight = spool.put(&url, fri_proof(1)).await.unwrap();
+        let released = spool.put(&other_url, fri_proof(1)).await.unwrap();
+        spool
+            .settle(
+                released,
+                &Err(ProofClientError::Transport(anyhow::anyhow!("down"))),
+            )
+            .await;
+
+        let claimed = spool.claim_pending().await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(
+            read_entry(&claimed[0].path).unwrap().sequencer_url,
+            other_url
+        );
+        // Claimed entries are in flight themselves now.
+        assert!(spool.claim_pending().await.unwrap().is_empty());
+
+        // A fresh spool (i.e. after a restart) replays everything.
+        drop((in_flight, claimed));
+        let reopened = ProofSpool::open(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reopened.claim_pending().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_entries_being_resubmitted_are_not_put_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = ProofSpool::open(dir.path().to_path_buf()).unwrap();
+        let url = Url::parse("http://localhost:3124").unwrap();
+
+        let ticket = spool.put(&url, fri_proof(1)).await.unwrap();
+        spool.release(ticket);
+        let claimed = spool.claim_pending().await.unwrap();
+        assert_eq!(claimed.len(), 1);
+
+        assert!(spool.put(&url, fri_proof(1)).await.is_err());
+        // The re-submitter's claim is kept.
+        assert!(spool.claim_pending().await.unwrap().is_empty());
+        spool
+            .settle(claimed.into_iter().next().unwrap(), &Ok(()))
+            .await;
+        assert_eq!(spooled_files(&spool), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_entries_are_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = ProofSpool::open(dir.path().to_path_buf()).unwrap();
+        let url = Url::parse("http://localhost:3124").unwrap();
+
+        let ticket = spool.put(&url, fri_proof(1)).await.unwrap();
+        spool.release(ticket);
+        std::fs::write(
+            dir.path().join("fri_2_0000000000000000.json"),
+            b"{\"sequencer_u",
+        )
+        .unwrap();
+
+        // No client serves the readable entry's sequencer, so it is kept as is.
+        assert_eq!(spool.resubmit_pending(&[]).await, 0);
+        assert!(dir
+            .path()
+            .join("fri_2_0000000000000000.json.unreadable")
+            .exists());
+        assert_eq!(spool.claim_pending().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use rand::Rng as _;
+use url::Url;
+use zkos_wrapper::SnarkWrapperProof;
+
+use crate::metrics::Method;
+use crate::{
+    FailedFriProofPayload, FriJobInputs, L2BatchNumber, ProofClient, ProofClientError,
+    ReleaseReason, SequencerCapabilities, SnarkProofInputs, SEQUENCER_CLIENT_METRICS,
+};
+
+/// How a [`RetryingProofClient`] retries one kind of operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per operation, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubled on every following one.
+    pub initial_backoff: Duration,
+    /// Upper bound for a single backoff.
+    pub max_backoff: Duration,
+    /// Overall time budget per operation. No retry is started if its backoff would end
+    /// past the deadline; requests in flight are bounded by the client's request timeout.
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            deadline: None,
+        }
+    }
+
+    /// Backoff before retry number `retry` (1-based): exponential, capped at
+    /// `max_backoff`, with the upper half jittered so provers sharing a sequencer
+    /// don't retry in lockstep.
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// [`ProofClient`] decorator that retries retryable failures (see
+/// [`ProofClientError::is_retryable`]) of the wrapped client with exponential backoff.
+///
+/// Picks and submits have separate policies: a failed submit throws away minutes of GPU
+/// time, so it usually deserves a much larger budget than a pick, which the prover's
+/// polling loop retries anyway.
+#[derive(Debug)]
+pub struct RetryingProofClient<C> {
+    inner: C,
+    pick_policy: RetryPolicy,
+    submit_policy: RetryPolicy,
+}
+
+impl<C: ProofClient> RetryingProofClient<C> {
+    pub fn new(inner: C, pick_policy: RetryPolicy, submit_policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            pick_policy,
+            submit_policy,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    async fn retry<T, F, Fut>(
+        &self,
+        method: Method,
+        policy: &RetryPolicy,
+        mut call: F,
+    ) -> Result<T, ProofClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProofClientError>>,
+    {
+        let started_at = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let err = match call().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            if !err.is_retryable() || attempt >= policy.max_attempts {
+                return Err(err);
+            }
+            let backoff = policy.backoff(attempt);
+            if policy
+                .deadline
+                .is_some_and(|deadline| started_at.elapsed() + backoff > deadline)
+            {
+                tracing::warn!(
+                    "Giving up {method:?} on sequencer {} after {attempt} attempts: deadline reached",
+                    self.inner.sequencer_url()
+                );
+                return Err(err);
+            }
+
+            tracing::warn!(
+                "{method:?} on sequencer {} failed (attempt {attempt}/{}): {err}; retrying in {backoff:?}",
+                self.inner.sequencer_url(),
+                policy.max_attempts
+            );
+            SEQUENCER_CLIENT_METRICS.retries[&method].inc();
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl<C: ProofClient> ProofClient for RetryingProofClient<C> {
+    fn sequencer_url(&self) -> &Url {
+        self.inner.sequencer_url()
+    }
+
+    fn long_poll_wait(&self) -> Option<Duration> {
+        self.inner.long_poll_wait()
+    }
+
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        self.retry(Method::Info, &self.pick_policy, || {
+            self.inner.negotiate_capabilities()
+        })
+        .await
+    }
+
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+        self.retry(Method::PickFri, &self.pick_policy, || {
+            self.inner.pick_fri_job()
+        })
+        .await
+    }
+
+    async fn submit_fri_proof(
+        &self,
+        batch_number: u32,
+        vk_hash: String,
+        proof: String,
+    ) -> Result<(), ProofClientError> {
+        self.retry(Method::SubmitFri, &self.submit_policy, || {
+            self.inner
+                .submit_fri_proof(batch_number, vk_hash.clone(), proof.clone())
+        })
+        .await
+    }
+
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        self.retry(Method::SubmitFailedFri, &self.submit_policy, || {
+            self.inner.submit_failed_fri_proof(payload.clone())
+        })
+        .await
+    }
+
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+        self.retry(Method::PickSnark, &self.pick_policy, || {
+            self.inner.pick_snark_job()
+        })
+        .await
+    }
+
+    async fn submit_snark_proof(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
+        proof: SnarkWrapperProof,
+    ) -> Result<(), ProofClientError> {
+        self.retry(Method::SubmitSnark, &self.submit_policy, || {
+            self.inner.submit_snark_proof(
+                from_batch_number,
+                to_batch_number,
+                vk_hash.clone(),
+                proof.clone(),
+            )
+        })
+        .await
+    }
+
+    // Heartbeats repeat on their own schedule; a failed one is not worth a retry.
+    async fn heartbeat_fri_job(&self, batch_number: u32) -> Result<bool, ProofClientError> {
+        self.inner.heartbeat_fri_job(batch_number).await
+    }
+
+    async fn heartbeat_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .heartbeat_snark_job(from_batch_number, to_batch_number)
+            .await
+    }
+
+    // A lost release only delays the job until its lease expires.
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .release_fri_job(batch_number, reason, message)
+            .await
+    }
+
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .release_snark_job(from_batch_number, to_batch_number, reason, message)
+            .await
+    }
+}
+
+/// Command-line options enabling [`RetryingProofClient`], shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RetryArgs {
+    /// Retry failed sequencer calls (timeouts, 5xx, connection errors) with exponential backoff
+    #[arg(long, default_value_t = false)]
+    pub retry: bool,
+    /// Attempts per pick request, including the first one
+    #[arg(long, default_value_t = 3)]
+    pub retry_pick_max_attempts: u32,
+    /// Overall time budget for one pick, retries included, in seconds
+    #[arg(long, default_value_t = 10)]
+    pub retry_pick_deadline_secs: u64,
+    /// Attempts per proof submission, including the first one
+    #[arg(long, default_value_t = 10)]
+    pub retry_submit_max_attempts: u32,
+    /// Overall time budget for one proof submission, retries included, in seconds
+    #[arg(long, default_value_t = 600)]
+    pub retry_submit_deadline_secs: u64,
+    /// Backoff before the first retry, in milliseconds; doubled on every following one
+    #[arg(long, default_value_t = 500)]
+    pub retry_initial_backoff_ms: u64,
+    /// Upper bound for a single backoff, in milliseconds
+    #[arg(long, default_value_t = 30_000)]
+    pub retry_max_backoff_ms: u64,
+}
+
+impl RetryArgs {
+    pub fn pick_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_pick_max_attempts,
+            initial_backoff: Duration::from_millis(self.retry_initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.retry_max_backoff_ms),
+            deadline: Some(Duration::from_secs(self.retry_pick_deadline_secs)),
+        }
+    }
+
+    pub fn submit_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_submit_max_attempts,
+            initial_backoff: Duration::from_millis(self.retry_initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.retry_max_backoff_ms),
+            deadline: Some(Duration::from_secs(self.retry_submit_deadline_secs)),
+        }
+    }
+
+    /// Wrap every client in a [`RetryingProofClient`] if `--retry` is set.
+    pub fn apply(
+        &self,
+        clients: Vec<Box<dyn ProofClient + Send + Sync>>,
+    ) -> Vec<Box<dyn ProofClient + Send + Sync>> {
+        if !self.retry {
+            return clients;
+        }
+        clients
+            .into_iter()
+            .map(|client| {
+                Box::new(RetryingProofClient::new(
+                    client,
+                    self.pick_policy(),
+                    self.submit_policy(),
+                )) as Box<dyn ProofClient + Send + Sync>
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_exponential_capped_and_jittered() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1_000),
+            deadline: None,
+        };
+        for _ in 0..100 {
+            let first = policy.backoff(1);
+            assert!((Duration::from_millis(50)..=Duration::from_millis(100)).contains(&first));
+            let third = policy.backoff(3);
+            assert!((Duration::from_millis(200)..=Duration::from_millis(400)).contains(&third));
+            let capped = policy.backoff(30);
+            assert!((Duration::from_millis(500)..=Duration::from_millis(1_000)).contains(&capped));
+        }
+    }
+}
@@ -0,0 +1,832 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use url::Url;
+use zkos_wrapper::SnarkWrapperProof;
+
+use crate::metrics::EndpointLabels;
+use crate::sequencer_endpoint::EndpointScheduling;
+use crate::{
+    FailedFriProofPayload, FriJobInputs, L2BatchNumber, LeasedJob, ProofClient, ProofClientError,
+    ReleaseReason, SequencerCapabilities, SnarkProofInputs, SEQUENCER_CLIENT_METRICS,
+};
+
+type PooledClients = Arc<Vec<Box<dyn ProofClient + Send + Sync>>>;
+
+type PickFn<T> = for<'a> fn(
+    &'a (dyn ProofClient + Send + Sync),
+) -> Pin<
+    Box<dyn Future<Output = Result<Option<T>, ProofClientError>> + Send + 'a>,
+>;
+
+/// Called with every failed pick of [`SequencerPool::pick_any_fri_job`] and
+/// [`SequencerPool::pick_any_snark_job`].
+pub type PickErrorHandler = fn(&dyn ProofClient, ProofClientError);
+
+/// Circuit breaker state of a pooled endpoint; exported as `0`, `1` and `2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointState {
+    /// Healthy, polled normally.
+    Closed = 0,
+    /// A probe call is in flight after the cooldown.
+    HalfOpen = 1,
+    /// Unhealthy, skipped until the cooldown ends.
+    Open = 2,
+}
+
+/// When a [`SequencerPool`] takes an endpoint out of rotation, and for how long.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerPolicy {
+    /// Consecutive failed calls (see [`ProofClientError::is_retryable`]) that open the
+    /// endpoint's circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit stays open before a single probe call is let through.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: EndpointState,
+    consecutive_failures: u32,
+    /// When the open circuit lets a probe through, or when a stuck probe is given up on.
+    retry_at: Instant,
+}
+
+/// Circuit breaker of one endpoint, shared between the pool and the endpoint's client.
+#[derive(Debug)]
+struct EndpointHealth {
+    url: Url,
+    policy: CircuitBreakerPolicy,
+    breaker: Mutex<Breaker>,
+}
+
+impl EndpointHealth {
+    fn new(url: Url, policy: CircuitBreakerPolicy) -> Self {
+        let health = Self {
+            url,
+            policy,
+            breaker: Mutex::new(Breaker {
+                state: EndpointState::Closed,
+                consecutive_failures: 0,
+                retry_at: Instant::now(),
+            }),
+        };
+        health.export(EndpointState::Closed);
+        health
+    }
+
+    fn breaker(&self) -> std::sync::MutexGuard<'_, Breaker> {
+        self.breaker.lock().expect("endpoint health poisoned")
+    }
+
+    fn export(&self, state: EndpointState) {
+        let labels = EndpointLabels {
+            url: self.url.to_string(),
+        };
+        SEQUENCER_CLIENT_METRICS.endpoint_state[&labels].set(state as u64);
+    }
+
+    /// Whether the endpoint may be polled at `now`; `Err` holds when it may be next.
+    fn available_at(&self, now: Instant) -> Result<(), Instant> {
+        let breaker = self.breaker();
+        match breaker.state {
+            EndpointState::Closed => Ok(()),
+            EndpointState::Open | EndpointState::HalfOpen if now >= breaker.retry_at => Ok(()),
+            EndpointState::Open | EndpointState::HalfOpen => Err(breaker.retry_at),
+        }
+    }
+
+    /// The endpoint was picked for polling: an open circuit lets this call through as a probe.
+    fn on_selected(&self, now: Instant) {
+        let mut breaker = self.breaker();
+        if breaker.state != EndpointState::Closed {
+            breaker.state = EndpointState::HalfOpen;
+            // Give up on a probe that never reports back after another cooldown.
+            breaker.retry_at = now + self.policy.cooldown;
+            drop(breaker);
+            tracing::info!("Probing unhealthy sequencer {}", self.url);
+            self.export(EndpointState::HalfOpen);
+        }
+    }
+
+    fn record<T>(&self, result: &Result<T, ProofClientError>) {
+        let mut breaker = self.breaker();
+        let previous = breaker.state;
+        match result {
+            Err(err) if err.is_retryable() => {
+                breaker.consecutive_failures += 1;
+                if previous != EndpointState::Closed
+                    || breaker.consecutive_failures >= self.policy.failure_threshold
+                {
+                    breaker.state = EndpointState::Open;
+                    breaker.retry_at = Instant::now() + self.policy.cooldown;
+                }
+            }
+            // Any answer that isn't a transport or server failure proves the endpoint alive.
+            _ => {
+                breaker.consecutive_failures = 0;
+                breaker.state = EndpointState::Closed;
+            }
+        }
+        let state = breaker.state;
+        drop(breaker);
+
+        if state == previous {
+            return;
+        }
+        match state {
+            EndpointState::Open => tracing::warn!(
+                "Sequencer {} is unhealthy, not polling it for {:?}",
+                self.url,
+                self.policy.cooldown
+            ),
+            EndpointState::Closed => tracing::info!("Sequencer {} is healthy again", self.url),
+            EndpointState::HalfOpen => {}
+        }
+        self.export(state);
+    }
+}
+
+/// [`ProofClient`] decorator feeding the outcome of picks and submissions to its endpoint's
+/// breaker. Heartbeats, releases and capability negotiation pass through: they don't decide
+/// whether the endpoint is worth polling.
+#[derive(Debug)]
+struct HealthTrackingClient<C> {
+    inner: Arc<C>,
+    /// `None` for the pool's untracked view of the endpoint.
+    health: Option<Arc<EndpointHealth>>,
+}
+
+impl<C> HealthTrackingClient<C> {
+    fn track<T>(&self, result: Result<T, ProofClientError>) -> Result<T, ProofClientError> {
+        if let Some(health) = &self.health {
+            health.record(&result);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<C: ProofClient> ProofClient for HealthTrackingClient<C> {
+    fn sequencer_url(&self) -> &Url {
+        self.inner.sequencer_url()
+    }
+
+    fn long_poll_wait(&self) -> Option<Duration> {
+        self.inner.long_poll_wait()
+    }
+
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        self.inner.negotiate_capabilities().await
+    }
+
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+        self.track(self.inner.pick_fri_job().await)
+    }
+
+    async fn submit_fri_proof(
+        &self,
+        batch_number: u32,
+        vk_hash: String,
+        proof: String,
+    ) -> Result<(), ProofClientError> {
+        self.track(
+            self.inner
+                .submit_fri_proof(batch_number, vk_hash, proof)
+                .await,
+        )
+    }
+
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        self.track(self.inner.submit_failed_fri_proof(payload).await)
+    }
+
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+        self.track(self.inner.pick_snark_job().await)
+    }
+
+    async fn submit_snark_proof(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        vk_hash: String,
+        proof: SnarkWrapperProof,
+    ) -> Result<(), ProofClientError> {
+        self.track(
+            self.inner
+                .submit_snark_proof(from_batch_number, to_batch_number, vk_hash, proof)
+                .await,
+        )
+    }
+
+    async fn heartbeat_fri_job(&self, batch_number: u32) -> Result<bool, ProofClientError> {
+        self.inner.heartbeat_fri_job(batch_number).await
+    }
+
+    async fn heartbeat_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .heartbeat_snark_job(from_batch_number, to_batch_number)
+            .await
+    }
+
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .release_fri_job(batch_number, reason, message)
+            .await
+    }
+
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        self.inner
+            .release_snark_job(from_batch_number, to_batch_number, reason, message)
+            .await
+    }
+}
+
+/// Schedules polling across several sequencers, skipping unhealthy ones.
+///
+/// Every endpoint has a circuit breaker: after [`CircuitBreakerPolicy::failure_threshold`]
+/// consecutive failed calls it is taken out of rotation for the cooldown, then a single
+/// probe call decides whether it rejoins (success) or sits out another cooldown. Healthy
+/// endpoints of the lowest [`EndpointScheduling::priority`] are polled in smooth weighted
+/// round-robin by [`EndpointScheduling::weight`].
+///
+/// The pool's clients report their picks and submissions to the breakers themselves, so
+/// those made outside of [`SequencerPool::next_client`] count as well. Calls that shouldn't
+/// count, like spool re-submissions, go through [`SequencerPool::untracked_clients`].
+///
+/// Instead of polling one endpoint at a time, [`SequencerPool::pick_any_fri_job`] and
+/// [`SequencerPool::pick_any_snark_job`] pick from every healthy endpoint of the lowest
+/// priority at once, no more often than [`SequencerPool::with_min_pick_interval`] allows
+/// per endpoint.
+pub struct SequencerPool {
+    clients: PooledClients,
+    untracked_clients: PooledClients,
+    health: Vec<Arc<EndpointHealth>>,
+    scheduling: Vec<EndpointScheduling>,
+    /// Smooth weighted round-robin state, one entry per endpoint.
+    current_weights: Mutex<Vec<i64>>,
+    min_pick_interval: Duration,
+    /// Earliest time of the next concurrent pick, one entry per endpoint.
+    next_pick_at: Mutex<Vec<Instant>>,
+}
+
+impl std::fmt::Debug for SequencerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SequencerPool")
+            .field("health", &self.health)
+            .field("scheduling", &self.scheduling)
+            .field("min_pick_interval", &self.min_pick_interval)
+            .finish()
+    }
+}
+
+impl SequencerPool {
+    /// # Panics
+    /// * if `endpoints` is empty
+    pub fn new(
+        endpoints: Vec<(Box<dyn ProofClient + Send + Sync>, EndpointScheduling)>,
+        policy: CircuitBreakerPolicy,
+    ) -> Self {
+        assert!(!endpoints.is_empty(), "sequencer pool needs an endpoint");
+        let mut clients = Vec::with_capacity(endpoints.len());
+        let mut untracked_clients = Vec::with_capacity(endpoints.len());
+        let mut health = Vec::with_capacity(endpoints.len());
+        let mut scheduling = Vec::with_capacity(endpoints.len());
+        for (client, endpoint_scheduling) in endpoints {
+            let endpoint_health = Arc::new(EndpointHealth::new(
+                client.sequencer_url().clone(),
+                policy.clone(),
+            ));
+            let client = Arc::new(client);
+            clients.push(Box::new(HealthTrackingClient {
+                inner: client.clone(),
+                health: Some(endpoint_health.clone()),
+            }) as Box<dyn ProofClient + Send + Sync>);
+            untracked_clients.push(Box::new(HealthTrackingClient {
+                inner: client,
+                health: None,
+            }) as Box<dyn ProofClient + Send + Sync>);
+            health.push(endpoint_health);
+            scheduling.push(endpoint_scheduling);
+        }
+        Self {
+            current_weights: Mutex::new(vec![0; clients.len()]),
+            min_pick_interval: Duration::ZERO,
+            next_pick_at: Mutex::new(vec![Instant::now(); clients.len()]),
+            clients: Arc::new(clients),
+            untracked_clients: Arc::new(untracked_clients),
+            health,
+            scheduling,
+        }
+    }
+
+    /// Rate limit concurrent picks: every endpoint is picked from at most once per `interval`.
+    pub fn with_min_pick_interval(mut self, interval: Duration) -> Self {
+        self.min_pick_interval = interval;
+        self
+    }
+
+    /// All clients of the pool, in the order they were given.
+    pub fn clients(&self) -> &Arc<Vec<Box<dyn ProofClient + Send + Sync>>> {
+        &self.clients
+    }
+
+    /// The pool's clients without health tracking, in the same order: for calls whose
+    /// outcome says nothing about whether to poll the endpoint, like spool re-submissions.
+    pub fn untracked_clients(&self) -> &Arc<Vec<Box<dyn ProofClient + Send + Sync>>> {
+        &self.untracked_clients
+    }
+
+    /// Negotiate capabilities with every endpoint (see
+    /// [`ProofClient::negotiate_capabilities`]). Failures are only logged: the endpoint's
+    /// client then keeps assuming a sequencer without capabilities.
+    pub async fn negotiate_capabilities(&self) {
+        for client in self.clients.iter() {
+            if let Err(e) = client.negotiate_capabilities().await {
+                tracing::warn!(
+                    "Failed to fetch capabilities of sequencer {}: {e}",
+                    client.sequencer_url()
+                );
+            }
+        }
+    }
+
+    /// Whether every endpoint long-polls (see [`ProofClient::long_poll_wait`]), so concurrent
+    /// picks wait on the sequencers rather than returning right away.
+    pub fn long_polls(&self) -> bool {
+        self.clients
+            .iter()
+            .all(|client| client.long_poll_wait().is_some())
+    }
+
+    /// Breaker state of every endpoint, in the order they were given.
+    pub fn states(&self) -> Vec<EndpointState> {
+        self.health
+            .iter()
+            .map(|health| health.breaker().state)
+            .collect()
+    }
+
+    /// The client to poll next. Waits while every endpoint is unhealthy, until the first
+    /// one is due for a probe.
+    pub async fn next_client(&self) -> &(dyn ProofClient + Send + Sync) {
+        let index = self.wait_for(|pool, now| pool.select(now)).await;
+        self.clients[index].as_ref()
+    }
+
+    /// Pick a FRI job from every preferred endpoint at once (see [`SequencerPool`]) and
+    /// return the first one, with the client it came from. Jobs picked by the other
+    /// endpoints are released in the background. `None` if no endpoint has a job.
+    pub async fn pick_any_fri_job(
+        &self,
+        on_error: PickErrorHandler,
+    ) -> Option<(&(dyn ProofClient + Send + Sync), FriJobInputs)> {
+        self.pick_any(
+            |client| client.pick_fri_job(),
+            |job| LeasedJob::Fri {
+                batch_number: job.batch_number,
+            },
+            on_error,
+        )
+        .await
+    }
+
+    /// Like [`SequencerPool::pick_any_fri_job`], for SNARK jobs.
+    pub async fn pick_any_snark_job(
+        &self,
+        on_error: PickErrorHandler,
+    ) -> Option<(&(dyn ProofClient + Send + Sync), SnarkProofInputs)> {
+        self.pick_any(
+            |client| client.pick_snark_job(),
+            |job| LeasedJob::Snark {
+                from_batch_number: job.from_batch_number,
+                to_batch_number: job.to_batch_number,
+            },
+            on_error,
+        )
+        .await
+    }
+
+    async fn pick_any<T: Send + 'static>(
+        &self,
+        pick: PickFn<T>,
+        leased_job: fn(&T) -> LeasedJob,
+        on_error: PickErrorHandler,
+    ) -> Option<(&(dyn ProofClient + Send + Sync), T)> {
+        let candidates = self
+            .wait_for(|pool, now| {
+                let candidates = pool.candidates(now)?;
+                for &index in &candidates {
+                    pool.health[index].on_selected(now);
+                }
+                Ok(candidates)
+            })
+            .await;
+
+        // Picks run in their own tasks, so the ones still in flight when the first job
+        // arrives complete (and their jobs get released) instead of being dropped mid-request.
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        for index in candidates {
+            let pick_at = self.reserve_pick(index);
+            let clients = self.clients.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep_until(pick_at.into()).await;
+                let result = pick(clients[index].as_ref()).await;
+                sender.send((index, result)).ok();
+            });
+        }
+        drop(sender);
+
+        while let Some((index, result)) = receiver.recv().await {
+            let client = self.clients[index].as_ref();
+            match result {
+                Ok(Some(job)) => {
+                    tokio::spawn(release_surplus_jobs(
+                        self.clients.clone(),
+                        receiver,
+                        leased_job,
+                        on_error,
+                    ));
+                    return Some((client, job));
+                }
+                Ok(None) => {}
+                Err(err) => on_error(client, err),
+            }
+        }
+        None
+    }
+
+    /// When the endpoint may be picked from next, honoring the pick rate limit.
+    fn reserve_pick(&self, index: usize) -> Instant {
+        let mut next_pick_at = self
+            .next_pick_at
+            .lock()
+            .expect("pick rate limiter poisoned");
+        let pick_at = next_pick_at[index].max(Instant::now());
+        next_pick_at[index] = pick_at + self.min_pick_interval;
+        pick_at
+    }
+
+    /// Wait until `attempt` finds an endpoint, sleeping while every endpoint is unhealthy.
+    async fn wait_for<T>(&self, attempt: impl Fn(&Self, Instant) -> Result<T, Instant>) -> T {
+        loop {
+            match attempt(self, Instant::now()) {
+                Ok(value) => return value,
+                Err(retry_at) => {
+                    tracing::debug!("All sequencers are unhealthy, waiting for the next probe");
+                    tokio::time::sleep_until(retry_at.into()).await;
+                }
+            }
+        }
+    }
+
+    /// Endpoints that may be polled at `now` and have the lowest priority among those;
+    /// `Err` holds when the first endpoint becomes available.
+    fn candidates(&self, now: Instant) -> Result<Vec<usize>, Instant> {
+        let mut next_retry: Option<Instant> = None;
+        let mut candidates = Vec::with_capacity(self.health.len());
+        for (index, health) in self.health.iter().enumerate() {
+            match health.available_at(now) {
+                Ok(()) => candidates.push(index),
+                Err(at) => next_retry = Some(next_retry.map_or(at, |next| next.min(at))),
+            }
+        }
+        let Some(priority) = candidates
+            .iter()
+            .map(|&index| self.scheduling[index].priority)
+            .min()
+        else {
+            return Err(next_retry.expect("pool has at least one endpoint"));
+        };
+        candidates.retain(|&index| self.scheduling[index].priority == priority);
+        Ok(candidates)
+    }
+
+    fn select(&self, now: Instant) -> Result<usize, Instant> {
+        let candidates = self.candidates(now)?;
+
+        // Smooth weighted round-robin (as in nginx): every candidate gains its weight, the
+        // richest one is picked and pays the total back.
+        let mut current_weights = self
+            .current_weights
+            .lock()
+            .expect("pool scheduler poisoned");
+        let mut total = 0;
+        let mut selected = candidates[0];
+        for &index in &candidates {
+            let weight = i64::from(self.scheduling[index].weight);
+            current_weights[index] += weight;
+            total += weight;
+            if current_weights[index] > current_weights[selected] {
+                selected = index;
+            }
+        }
+        current_weights[selected] -= total;
+        drop(current_weights);
+
+        self.health[selected].on_selected(now);
+        Ok(selected)
+    }
+}
+
+/// Hand back the jobs of picks that lost the race of [`SequencerPool::pick_any`].
+async fn release_surplus_jobs<T>(
+    clients: PooledClients,
+    mut receiver: mpsc::UnboundedReceiver<(usize, Result<Option<T>, ProofClientError>)>,
+    leased_job: fn(&T) -> LeasedJob,
+    on_error: PickErrorHandler,
+) {
+    while let Some((index, result)) = receiver.recv().await {
+        let client = clients[index].as_ref();
+        match result {
+            Ok(Some(job)) => {
+                leased_job(&job)
+                    .release(
+                        client,
+                        ReleaseReason::Busy,
+                        "prover picked a job from another sequencer".to_string(),
+                    )
+                    .await
+            }
+            Ok(None) => {}
+            Err(err) => on_error(client, err),
+        }
+    }
+}
+
+/// Command-line options of the [`SequencerPool`], shared by the prover binaries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct PoolArgs {
+    /// Consecutive failed calls after which a sequencer is skipped for the cooldown
+    #[arg(long, default_value_t = 3)]
+    pub sequencer_failure_threshold: u32,
+    /// How long an unhealthy sequencer is skipped before it is probed again, in seconds
+    #[arg(long, default_value_t = 30)]
+    pub sequencer_cooldown_secs: u64,
+}
+
+impl PoolArgs {
+    pub fn policy(&self) -> CircuitBreakerPolicy {
+        CircuitBreakerPolicy {
+            failure_threshold: self.sequencer_failure_threshold,
+            cooldown: Duration::from_secs(self.sequencer_cooldown_secs),
+        }
+    }
+
+    /// Build the pool over `clients`, scheduled as their `endpoints` (in the same order) say.
+    pub fn build(
+        &self,
+        clients: Vec<Box<dyn ProofClient + Send + Sync>>,
+        endpoints: &[EndpointScheduling],
+    ) -> SequencerPool {
+        assert_eq!(clients.len(), endpoints.len(), "one client per endpoint");
+        SequencerPool::new(
+            clients.into_iter().zip(endpoints.iter().copied()).collect(),
+            self.policy(),
+        )
+    }
+}
+
+/// Command-line options of concurrent picking (see [`SequencerPool::pick_any_fri_job`]).
+#[derive(Debug, Clone, clap::Args)]
+pub struct ConcurrentPickArgs {
+    /// Pick from all sequencers at once instead of polling them one by one; jobs picked
+    /// after the first one are released back
+    #[arg(long, default_value_t = false)]
+    pub concurrent_pick: bool,
+    /// Minimum time between two concurrent picks from the same sequencer, in milliseconds
+    #[arg(long, default_value_t = 1_000)]
+    pub min_pick_interval_ms: u64,
+}
+
+impl ConcurrentPickArgs {
+    pub fn min_pick_interval(&self) -> Duration {
+        Duration::from_millis(self.min_pick_interval_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeClient {
+        url: Url,
+        healthy: Arc<AtomicBool>,
+    }
+
+    impl FakeClient {
+        fn respond<T>(&self, ok: T) -> Result<T, ProofClientError> {
+            if self.healthy.load(Ordering::Relaxed) {
+                Ok(ok)
+            } else {
+                Err(ProofClientError::Transport(anyhow::anyhow!(
+                    "connection refused"
+                )))
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ProofClient for FakeClient {
+        fn sequencer_url(&self) -> &Url {
+            &self.url
+        }
+
+        async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
+            self.respond(None)
+        }
+
+        async fn submit_fri_proof(
+            &self,
+            _: u32,
+            _: String,
+            _: String,
+        ) -> Result<(), ProofClientError> {
+            self.respond(())
+        }
+
+        async fn submit_failed_fri_proof(
+            &self,
+            _: FailedFriProofPayload,
+        ) -> Result<(), ProofClientError> {
+            self.respond(())
+        }
+
+        async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
+            self.respond(None)
+        }
+
+        async fn heartbeat_fri_job(&self, _: u32) -> Result<bool, ProofClientError> {
+            self.respond(true)
+        }
+
+        async fn release_fri_job(
+            &self,
+            _: u32,
+            _: ReleaseReason,
+            _: String,
+        ) -> Result<bool, ProofClientError> {
+            self.respond(true)
+        }
+
+        async fn submit_snark_proof(
+            &self,
+            _: L2BatchNumber,
+            _: L2BatchNumber,
+            _: String,
+            _: SnarkWrapperProof,
+        ) -> Result<(), ProofClientError> {
+            self.respond(())
+        }
+    }
+
+    fn pool(endpoints: &[(u32, u32)], cooldown: Duration) -> (SequencerPool, Vec<Arc<AtomicBool>>) {
+        let mut flags = vec![];
+        let endpoints = endpoints
+            .iter()
+            .enumerate()
+            .map(|(i, &(weight, priority))| {
+                let healthy = Arc::new(AtomicBool::new(true));
+                flags.push(healthy.clone());
+                let client = FakeClient {
+                    url: Url::parse(&format!("http://sequencer{i}:3124")).unwrap(),
+                    healthy,
+                };
+                (
+                    Box::new(client) as Box<dyn ProofClient + Send + Sync>,
+                    EndpointScheduling { weight, priority },
+                )
+            })
+            .collect();
+        let policy = CircuitBreakerPolicy {
+            failure_threshold: 2,
+            cooldown,
+        };
+        (SequencerPool::new(endpoints, policy), flags)
+    }
+
+    #[test]
+    fn test_weighted_round_robin_within_priority() {
+        let (pool, _) = pool(&[(1, 0), (3, 0), (5, 1)], Duration::from_secs(30));
+        let now = Instant::now();
+        let picks: Vec<_> = (0..8).map(|_| pool.select(now).unwrap()).collect();
+        assert_eq!(picks.iter().filter(|&&i| i == 0).count(), 2);
+        assert_eq!(picks.iter().filter(|&&i| i == 1).count(), 6);
+        // Smooth: the heavy endpoint never gets all picks in a row.
+        assert!(picks.windows(4).all(|window| window.contains(&0)));
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_endpoint_is_skipped_then_probed() {
+        let (pool, flags) = pool(&[(1, 0), (1, 1)], Duration::from_millis(50));
+        flags[0].store(false, Ordering::Relaxed);
+
+        for _ in 0..2 {
+            let client = pool.next_client().await;
+            assert_eq!(client.sequencer_url().host_str(), Some("sequencer0"));
+            client.pick_fri_job().await.unwrap_err();
+        }
+        assert_eq!(pool.states()[0], EndpointState::Open);
+
+        // Fails over to the lower-priority endpoint during the cooldown.
+        let client = pool.next_client().await;
+        assert_eq!(client.sequencer_url().host_str(), Some("sequencer1"));
+
+        // After the cooldown a failed probe reopens the circuit right away...
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let client = pool.next_client().await;
+        assert_eq!(pool.states()[0], EndpointState::HalfOpen);
+        client.pick_fri_job().await.unwrap_err();
+        assert_eq!(pool.states()[0], EndpointState::Open);
+
+        // ...and a successful one closes it.
+        flags[0].store(true, Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let client = pool.next_client().await;
+        assert_eq!(client.sequencer_url().host_str(), Some("sequencer0"));
+        client.pick_fri_job().await.unwrap();
+        assert_eq!(pool.states()[0], EndpointState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_only_picks_and_submissions_count() {
+        let (pool, flags) = pool(&[(1, 0)], Duration::from_secs(30));
+        flags[0].store(false, Ordering::Relaxed);
+
+        let client = pool.clients()[0].as_ref();
+        let untracked = pool.untracked_clients()[0].as_ref();
+        for _ in 0..3 {
+            client.heartbeat_fri_job(1).await.unwrap_err();
+            client
+                .release_fri_job(1, ReleaseReason::Operator, String::new())
+                .await
+                .unwrap_err();
+            untracked
+                .submit_fri_proof(1, String::new(), String::new())
+                .await
+                .unwrap_err();
+        }
+        assert_eq!(pool.states()[0], EndpointState::Closed);
+
+        for _ in 0..2 {
+            client
+                .submit_fri_proof(1, String::new(), String::new())
+                .await
+                .unwrap_err();
+        }
+        assert_eq!(pool.states()[0], EndpointState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_probe_when_all_endpoints_are_unhealthy() {
+        let (pool, flags) = pool(&[(1, 0)], Duration::from_millis(50));
+        flags[0].store(false, Ordering::Relaxed);
+        for _ in 0..2 {
+            pool.next_client().await.pick_fri_job().await.unwrap_err();
+        }
+
+        let started_at = Instant::now();
+        pool.next_client().await;
+        assert!(started_at.elapsed() >= Duration::from_millis(40));
+        assert_eq!(pool.states()[0], EndpointState::HalfOpen);
+    }
+}
@@ -1,27 +1,44 @@
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
-use crate::metrics::Method;
+use crate::auth::{endpoint_auth_provider, AuthProvider};
+use crate::metrics::{observe_payload_size, Method};
 use crate::sequencer_endpoint::SequencerEndpoint;
+use crate::snark_job_decoder::receive_snark_job;
+use crate::telemetry::trace_context_headers;
+use crate::transport::{
+    announced_digest, body_format, content_digest, decode_frames, encode_frames, verify_digest,
+    BINARY_CONTENT_TYPE, CONTENT_DIGEST, JSON_CONTENT_TYPE,
+};
+use crate::{payload_digest, submission_idempotency_key, L2BatchNumber, SEQUENCER_CLIENT_METRICS};
 use crate::{
-    FailedFriProofPayload, FriJobInputs, GetSnarkProofPayload, NextFriProverJobPayload,
-    PeekableProofClient, ProofClient, SnarkProofInputs, SubmitFriProofPayload,
-    SubmitSnarkProofPayload,
+    FailedFriProofPayload, FileBasedProofClient, FriJobHeader, FriJobInputs, FriProofHeader,
+    NextFriProverJobPayload, PayloadEncoding, PeekableProofClient, ProofClient, ProofClientError,
+    ReleaseJobPayload, ReleaseReason, RequestTimeouts, SequencerCapabilities, SequencerFeature,
+    SnarkJobHeader, SnarkProofInputs, SubmitFriProofPayload, SubmitSnarkProofPayload,
+    DEFAULT_LONG_POLL_WAIT, SUPPORTED_API_VERSIONS,
 };
-use crate::{L2BatchNumber, SEQUENCER_CLIENT_METRICS};
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::header::{ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE};
 use reqwest::StatusCode;
+use sha2::{Digest as _, Sha256};
 use url::Url;
 use zkos_wrapper::SnarkWrapperProof;
 
 #[derive(Debug)]
 pub struct SequencerProofClient {
     client: reqwest::Client,
+    /// Authorizes every request, if the endpoint has credentials.
+    auth: Option<Arc<dyn AuthProvider>>,
     endpoint: Url,
     prover_name: String,
     supported_vk_hashes: Vec<String>,
+    timeouts: RequestTimeouts,
+    long_poll_wait: Duration,
+    /// Set by [`ProofClient::negotiate_capabilities`], if the sequencer advertises any.
+    capabilities: RwLock<Option<SequencerCapabilities>>,
 }
 
 impl SequencerProofClient {
@@ -30,12 +47,15 @@ impl SequencerProofClient {
     /// # Arguments
     /// * `endpoint` - The sequencer endpoint (URL + optional credentials)
     /// * `prover_name` - The name of the prover (used for identification in sequencer prover api)
-    /// * `timeout` - Optional timeout for requests (None defaults to 2 seconds)
+    /// * `timeout` - Optional timeout for all requests (None defaults to 2 seconds)
     /// * `supported_vk_hashes` - VK hashes this prover supports; sent on pick requests so the
     ///   sequencer only assigns jobs of these versions. Empty means no declaration - the
     ///   sequencer will offer jobs of any version.
     ///
     /// # Errors
+    /// * if the endpoint's credentials would go over plain HTTP without
+    ///   [`SequencerEndpoint::allow_insecure_credentials`]
+    /// * if its credentials or TLS files can't be read
     /// * if building the reqwest client fails
     pub fn new(
         endpoint: SequencerEndpoint,
@@ -43,45 +63,80 @@ impl SequencerProofClient {
         timeout: Option<Duration>,
         supported_vk_hashes: Vec<String>,
     ) -> anyhow::Result<Self> {
-        let mut headers = HeaderMap::new();
-
-        // Add Basic Auth header if credentials are present
-        if let Some(creds) = &endpoint.credentials {
-            use secrecy::ExposeSecret;
-            let auth_value = format!(
-                "Basic {}",
-                STANDARD.encode(format!(
-                    "{}:{}",
-                    creds.username,
-                    creds.password.expose_secret()
-                ))
-            );
-            headers.insert(
-                AUTHORIZATION,
-                HeaderValue::from_str(&auth_value).context("Failed to create auth header value")?,
-            );
-        }
+        Self::new_with_timeouts(
+            endpoint,
+            prover_name,
+            timeout.map_or_else(RequestTimeouts::default, RequestTimeouts::uniform),
+            supported_vk_hashes,
+        )
+    }
 
-        let client = reqwest::Client::builder()
-            .timeout(timeout.unwrap_or(Duration::from_secs(2)))
-            .default_headers(headers)
-            .build()
-            .context("Failed to build reqwest client")?;
+    /// Like [`Self::new`], with separate timeouts per kind of request.
+    pub fn new_with_timeouts(
+        endpoint: SequencerEndpoint,
+        prover_name: String,
+        timeouts: RequestTimeouts,
+        supported_vk_hashes: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        endpoint.check_credentials_transport()?;
+        let auth = endpoint_auth_provider(&endpoint)?;
+
+        // Requests without a timeout of their own are the small ones.
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(timeouts.connect)
+            .timeout(timeouts.pick);
+        if let (Some(cert), Some(key)) = (&endpoint.tls.cert, &endpoint.tls.key) {
+            let read = |path: &std::path::PathBuf| {
+                std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+            };
+            let identity = reqwest::Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)
+                .context("Invalid TLS client certificate or key")?;
+            builder = builder.identity(identity);
+        }
+        if let Some(ca) = &endpoint.tls.ca {
+            let bundle = std::fs::read(ca)
+                .with_context(|| format!("Failed to read CA bundle {}", ca.display()))?;
+            for certificate in reqwest::Certificate::from_pem_bundle(&bundle)
+                .with_context(|| format!("Invalid CA bundle {}", ca.display()))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        let client = builder.build().context("Failed to build reqwest client")?;
 
         Ok(Self {
             client,
+            auth,
             endpoint: endpoint.url,
             prover_name,
             supported_vk_hashes,
+            timeouts,
+            long_poll_wait: DEFAULT_LONG_POLL_WAIT,
+            capabilities: RwLock::new(None),
         })
     }
 
+    /// Authorize requests with `auth` instead of the endpoint's credentials.
+    pub fn with_auth_provider(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// How long picks ask a sequencer advertising [`SequencerFeature::LongPoll`] to wait
+    /// for a job (default [`DEFAULT_LONG_POLL_WAIT`]); `Duration::ZERO` disables long-polling.
+    pub fn with_long_poll_wait(mut self, wait: Duration) -> Self {
+        self.long_poll_wait = wait;
+        self
+    }
+
     /// Create multiple sequencer proof clients from a list of endpoints.
     ///
+    /// `file://` endpoints get a [`FileBasedProofClient`] over that directory instead.
+    ///
     /// # Arguments
     /// * `endpoints` - A vector of sequencer endpoints
     /// * `prover_name` - The name of the prover (used for identification in sequencer prover api)
-    /// * `timeout` - Optional timeout for requests (None defaults to 2 seconds)
+    /// * `timeouts` - Timeouts per kind of request
     /// * `supported_vk_hashes` - VK hashes this prover supports; sent on pick requests so the
     ///   sequencer only assigns jobs of these versions. Empty means no declaration - the
     ///   sequencer will offer jobs of any version.
@@ -92,7 +147,7 @@ impl SequencerProofClient {
     pub fn new_clients(
         endpoints: Vec<SequencerEndpoint>,
         prover_name: String,
-        timeout: Option<Duration>,
+        timeouts: RequestTimeouts,
         supported_vk_hashes: Vec<String>,
     ) -> anyhow::Result<Vec<Box<dyn ProofClient + Send + Sync>>> {
         if endpoints.is_empty() {
@@ -104,10 +159,18 @@ impl SequencerProofClient {
             .enumerate()
             .map(|(i, endpoint)| {
                 let url = endpoint.url.clone();
-                let client = SequencerProofClient::new(
+                if url.scheme() == "file" {
+                    let client = FileBasedProofClient::from_url(&url, supported_vk_hashes.clone())
+                        .with_context(|| {
+                            format!("Failed to create file-based client #{i} at url {url:?}")
+                        })?;
+                    return Ok(Box::new(client) as Box<dyn ProofClient + Send + Sync>);
+                }
+
+                let client = SequencerProofClient::new_with_timeouts(
                     endpoint,
                     prover_name.clone(),
-                    timeout,
+                    timeouts,
                     supported_vk_hashes.clone(),
                 )
                 .with_context(|| {
@@ -127,42 +190,221 @@ impl SequencerProofClient {
     /// # Errors
     /// * if serialization/deserialization fails (needed for conversion)
     pub fn serialize_snark_proof(&self, proof: &SnarkWrapperProof) -> anyhow::Result<String> {
-        let (_, serialized_proof) = crypto_codegen::serialize_proof(proof);
-
-        let byte_serialized_proof = serialized_proof
-            .iter()
-            .flat_map(|chunk| {
-                let mut buf = [0u8; 32];
-                chunk.to_big_endian(&mut buf);
-                buf
+        Ok(crate::serialize_snark_proof(proof))
+    }
+
+    fn capabilities(&self) -> std::sync::RwLockReadGuard<'_, Option<SequencerCapabilities>> {
+        self.capabilities
+            .read()
+            .expect("sequencer capabilities poisoned")
+    }
+
+    /// Whether to use an optional `feature`: if the sequencer's capabilities are unknown,
+    /// it is tried (and its absence detected from the response).
+    fn may_use(&self, feature: SequencerFeature) -> bool {
+        self.capabilities()
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.supports(feature))
+    }
+
+    /// Long-polling changes what an empty pick means, so unlike [`Self::may_use`] features
+    /// it is only used once the sequencer advertised it.
+    fn long_poll(&self) -> Option<Duration> {
+        let advertised = self
+            .capabilities()
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.supports(SequencerFeature::LongPoll));
+        (advertised && !self.long_poll_wait.is_zero()).then_some(self.long_poll_wait)
+    }
+
+    /// Whether jobs and proofs go as binary frames (see [`crate::transport`]). Like
+    /// long-polling, only used once the sequencer advertised it.
+    fn binary_payloads(&self) -> bool {
+        self.capabilities()
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.supports(SequencerFeature::BinaryPayloads))
+    }
+
+    /// Compression for binary request bodies.
+    fn upload_encoding(&self) -> PayloadEncoding {
+        self.capabilities()
+            .as_ref()
+            .map_or(PayloadEncoding::Identity, |capabilities| {
+                capabilities.upload_encoding()
             })
-            .collect::<Vec<u8>>();
+    }
 
-        Ok(STANDARD.encode(byte_serialized_proof))
+    /// A request to the sequencer, authorized and carrying the current trace context.
+    fn request(&self, method: reqwest::Method, url: Url) -> reqwest::RequestBuilder {
+        let request = self
+            .client
+            .request(method, url)
+            .headers(trace_context_headers());
+        match self.auth.as_ref().and_then(|auth| auth.authorization()) {
+            Some(authorization) => request.header(AUTHORIZATION, authorization),
+            None => request,
+        }
     }
 
-    /// Constructs a prover API endpoint URL.
-    fn build_url(&self, path: &str) -> anyhow::Result<Url> {
-        let url = self
-            .endpoint
-            .join("prover-jobs/v1/")?
-            .join(path)
-            .with_context(|| format!("Failed to build URL for path: {path}"))?;
-        Ok(url)
+    fn get(&self, url: Url) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, url)
+    }
+
+    fn post(&self, url: Url) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, url)
+    }
+
+    /// Constructs a prover API endpoint URL, for the negotiated API version.
+    fn build_url(&self, path: &str) -> Result<Url, ProofClientError> {
+        let base = match self.capabilities().as_ref() {
+            Some(capabilities) => format!("prover-jobs/{}/", capabilities.api_version),
+            None => format!("prover-jobs/{}/", SUPPORTED_API_VERSIONS[0]),
+        };
+        self.endpoint
+            .join(&base)
+            .and_then(|base| base.join(path))
+            .with_context(|| format!("Failed to build URL for path: {path}"))
+            .map_err(ProofClientError::Transport)
     }
 
     /// Query string for pick requests: prover id plus, if declared, the supported VK hashes.
     /// Sequencers aware of `supported_vk_hashes` only assign jobs of these versions;
-    /// older sequencers ignore the parameter.
+    /// older sequencers ignore the parameter. Long-polling sequencers also get `wait_ms`.
     fn pick_query(&self) -> String {
-        if self.supported_vk_hashes.is_empty() {
-            format!("id={}", self.prover_name)
-        } else {
-            format!(
-                "id={}&supported_vk_hashes={}",
-                self.prover_name,
+        let mut query = format!("id={}", self.prover_name);
+        if !self.supported_vk_hashes.is_empty() {
+            query.push_str(&format!(
+                "&supported_vk_hashes={}",
                 self.supported_vk_hashes.join(",")
-            )
+            ));
+        }
+        if let Some(wait) = self.long_poll() {
+            query.push_str(&format!("&wait_ms={}", wait.as_millis()));
+        }
+        query
+    }
+
+    /// A pick request; one the sequencer may hold open gets that much longer to complete.
+    /// Sequencers that can send binary jobs are asked to, compressed.
+    fn pick_request(&self, url: Url) -> reqwest::RequestBuilder {
+        let mut request = self.post(url);
+        if let Some(wait) = self.long_poll() {
+            request = request.timeout(self.timeouts.pick + wait);
+        }
+        if self.binary_payloads() {
+            let encodings: Vec<_> = PayloadEncoding::SUPPORTED
+                .iter()
+                .map(|encoding| encoding.as_str())
+                .collect();
+            request = request
+                .header(
+                    ACCEPT,
+                    format!("{BINARY_CONTENT_TYPE}, {JSON_CONTENT_TYPE};q=0.5"),
+                )
+                .header(ACCEPT_ENCODING, encodings.join(", "));
+        }
+        request
+    }
+
+    /// Attach a proof body, compressing binary ones as the sequencer accepts, with a timeout
+    /// for its size.
+    fn with_body(
+        &self,
+        request: reqwest::RequestBuilder,
+        method: Method,
+        body: Body,
+    ) -> Result<reqwest::RequestBuilder, ProofClientError> {
+        let (content_type, encoding, decoded) = match body {
+            Body::Json(bytes) => (JSON_CONTENT_TYPE, PayloadEncoding::Identity, bytes),
+            Body::Binary(bytes) => (BINARY_CONTENT_TYPE, self.upload_encoding(), bytes),
+        };
+        let decoded_len = decoded.len();
+        let wire = encoding.compress(decoded).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("Failed to compress payload: {e}"))
+        })?;
+        observe_payload_size(method, decoded_len, wire.len());
+
+        let request = request
+            .timeout(self.timeouts.submit(wire.len()))
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_DIGEST, content_digest(&wire));
+        let request = match encoding {
+            PayloadEncoding::Identity => request,
+            encoding => request.header(CONTENT_ENCODING, encoding.as_str()),
+        };
+        Ok(request.body(wire))
+    }
+}
+
+/// Header carrying [`submission_idempotency_key`] on proof submissions.
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Outcome of a proof submission answered with `status`. `409` means the sequencer already
+/// has the proof, e.g. when retrying a submission whose response was lost, so it counts as
+/// accepted.
+fn submission_result(
+    status: StatusCode,
+    method: Method,
+    proof: impl FnOnce() -> String,
+) -> Result<(), ProofClientError> {
+    if status == StatusCode::CONFLICT {
+        tracing::info!(
+            "Sequencer already has the {}, counting the submission as accepted",
+            proof()
+        );
+        SEQUENCER_CLIENT_METRICS.duplicate_submissions[&method].inc();
+        Ok(())
+    } else if status.is_success() {
+        Ok(())
+    } else {
+        Err(ProofClientError::from_status(status))
+    }
+}
+
+/// A request body of the prover API.
+enum Body {
+    Json(Vec<u8>),
+    /// Frames of a binary payload, see [`crate::transport`].
+    Binary(Vec<u8>),
+}
+
+/// Read a job body, checking its `Content-Digest` and undoing its `Content-Encoding`.
+/// Binary bodies come back as [`Body::Binary`], anything else as [`Body::Json`].
+async fn receive(resp: reqwest::Response, method: Method) -> Result<Body, ProofClientError> {
+    let (binary, encoding) = body_format(resp.headers())?;
+    let announced = announced_digest(resp.headers())?;
+
+    let wire = resp.bytes().await?.to_vec();
+    verify_digest(announced.as_deref(), Sha256::digest(&wire))?;
+    let wire_len = wire.len();
+    let decoded = encoding.decompress(wire).map_err(|e| {
+        ProofClientError::MalformedPayload(format!("Failed to decompress payload: {e}"))
+    })?;
+    observe_payload_size(method, decoded.len(), wire_len);
+
+    Ok(if binary {
+        Body::Binary(decoded)
+    } else {
+        Body::Json(decoded)
+    })
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, ProofClientError> {
+    serde_json::from_slice(body).map_err(|e| ProofClientError::MalformedPayload(e.to_string()))
+}
+
+impl Body {
+    fn json<T: serde::Serialize>(payload: &T) -> Self {
+        Self::Json(serde_json::to_vec(payload).expect("payloads serialize to JSON"))
+    }
+
+    fn into_fri_job(self) -> Result<FriJobInputs, ProofClientError> {
+        match self {
+            Self::Json(body) => parse_json::<NextFriProverJobPayload>(&body)?.try_into(),
+            Self::Binary(body) => {
+                let (header, blobs): (FriJobHeader, _) = decode_frames(&body)?;
+                header.into_inputs(blobs)
+            }
         }
     }
 }
@@ -173,219 +415,402 @@ impl ProofClient for SequencerProofClient {
         &self.endpoint
     }
 
-    async fn pick_fri_job(&self) -> anyhow::Result<Option<FriJobInputs>> {
+    fn long_poll_wait(&self) -> Option<Duration> {
+        self.long_poll()
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn negotiate_capabilities(
+        &self,
+    ) -> Result<Option<SequencerCapabilities>, ProofClientError> {
+        // Unversioned, so clients can learn the version before talking it.
+        let url = self
+            .endpoint
+            .join("prover-jobs/info")
+            .context("Failed to build URL for the info route")
+            .map_err(ProofClientError::Transport)?;
+
+        let started_at = Instant::now();
+
+        let resp = self.get(url).send().await?;
+
+        SEQUENCER_CLIENT_METRICS.time_taken[&Method::Info]
+            .observe(started_at.elapsed().as_secs_f64());
+
+        let capabilities: SequencerCapabilities = match resp.status() {
+            StatusCode::OK => resp.json().await?,
+            StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_IMPLEMENTED => {
+                tracing::info!(
+                    "Sequencer {} does not advertise capabilities, assuming prover API {}",
+                    self.endpoint,
+                    SUPPORTED_API_VERSIONS[0]
+                );
+                return Ok(None);
+            }
+            s => return Err(ProofClientError::from_status(s)),
+        };
+        capabilities.report(&self.endpoint, &self.supported_vk_hashes);
+
+        if capabilities.is_api_version_supported() {
+            *self
+                .capabilities
+                .write()
+                .expect("sequencer capabilities poisoned") = Some(capabilities.clone());
+        } else {
+            tracing::error!(
+                "Sequencer {} serves prover API {}, but this prover only speaks {:?}; \
+                 requests to it will likely fail",
+                self.endpoint,
+                capabilities.api_version,
+                SUPPORTED_API_VERSIONS
+            );
+        }
+        Ok(Some(capabilities))
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn pick_fri_job(&self) -> Result<Option<FriJobInputs>, ProofClientError> {
         let url = self.build_url(&format!("FRI/pick?{}", self.pick_query()))?;
 
         let started_at = Instant::now();
 
-        let resp = self
-            .client
-            .post(url.clone())
-            .send()
-            .await
-            .context("Pick Fri Job request failed")?;
+        let resp = self.pick_request(url).send().await?;
 
         SEQUENCER_CLIENT_METRICS.time_taken[&Method::PickFri]
             .observe(started_at.elapsed().as_secs_f64());
 
         match resp.status() {
             StatusCode::OK => {
-                let body: NextFriProverJobPayload = resp.json().await?;
-                let data = STANDARD
-                    .decode(&body.prover_input)
-                    .map_err(|e| anyhow!("Failed to decode batch data: {e}"))?;
-                Ok(Some(FriJobInputs {
-                    batch_number: body.batch_number,
-                    vk_hash: body.vk_hash,
-                    prover_input: data,
-                }))
+                let job = receive(resp, Method::PickFri).await?.into_fri_job()?;
+                tracing::info!(
+                    "Picked FRI job for batch {} from {}, prover input sha-256 {}",
+                    job.batch_number,
+                    self.endpoint,
+                    payload_digest(&job.prover_input)
+                );
+                Ok(Some(job))
             }
             StatusCode::NO_CONTENT => Ok(None),
-            s => Err(anyhow!(
-                "Unexpected status {s} when fetching next batch at address {url}"
-            )),
+            s => Err(ProofClientError::from_status(s)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
     async fn submit_fri_proof(
         &self,
         batch_number: u32,
         vk_hash: String,
         proof: String,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), ProofClientError> {
         let url = self.build_url(&format!("FRI/submit?id={}", self.prover_name))?;
 
-        let payload = SubmitFriProofPayload {
-            batch_number: batch_number as u64,
-            vk_hash,
-            proof,
+        let proof_bytes = STANDARD.decode(&proof).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("FRI proof is not base64: {e}"))
+        })?;
+        tracing::info!(
+            "Submitting FRI proof for batch {batch_number} to {}, proof sha-256 {}",
+            self.endpoint,
+            payload_digest(&proof_bytes)
+        );
+        let idempotency_key = submission_idempotency_key(
+            batch_number as u64,
+            batch_number as u64,
+            &vk_hash,
+            &proof_bytes,
+        );
+        let body = if self.binary_payloads() {
+            let header = FriProofHeader {
+                batch_number: batch_number as u64,
+                vk_hash,
+            };
+            Body::Binary(encode_frames(&header, &[&proof_bytes]))
+        } else {
+            Body::json(&SubmitFriProofPayload {
+                batch_number: batch_number as u64,
+                vk_hash,
+                proof,
+            })
         };
+        let request = self
+            .with_body(self.post(url), Method::SubmitFri, body)?
+            .header(IDEMPOTENCY_KEY, idempotency_key);
 
         let started_at = Instant::now();
 
-        let resp = self
-            .client
-            .post(url.clone())
-            .json(&payload)
-            .send()
-            .await
-            .context("Submit Fri Proof request failed")?;
+        let resp = request.send().await?;
 
         SEQUENCER_CLIENT_METRICS.time_taken[&Method::SubmitFri]
             .observe(started_at.elapsed().as_secs_f64());
 
-        if resp.status().is_success() {
-            Ok(())
-        } else {
-            Err(anyhow!(
-                "Server returned {} when submitting proof to {}",
-                resp.status(),
-                url
-            ))
-        }
+        submission_result(resp.status(), Method::SubmitFri, || {
+            format!("FRI proof of batch {batch_number}")
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn submit_failed_fri_proof(
+        &self,
+        payload: FailedFriProofPayload,
+    ) -> Result<(), ProofClientError> {
+        // Keyed by the raw proof, like proof submissions.
+        let proof_bytes = STANDARD.decode(&payload.proof).map_err(|e| {
+            ProofClientError::MalformedPayload(format!("failed FRI proof is not base64: {e}"))
+        })?;
+        let url = self.build_url(&format!(
+            "FRI/{}/failed?id={}",
+            payload.batch_number, self.prover_name
+        ))?;
+
+        let batch_number = payload.batch_number;
+        let idempotency_key =
+            submission_idempotency_key(batch_number, batch_number, &payload.vk_hash, &proof_bytes);
+        let request = self
+            .with_body(
+                self.post(url),
+                Method::SubmitFailedFri,
+                Body::json(&payload),
+            )?
+            .header(IDEMPOTENCY_KEY, idempotency_key);
+
+        let started_at = Instant::now();
+
+        let resp = request.send().await?;
+
+        SEQUENCER_CLIENT_METRICS.time_taken[&Method::SubmitFailedFri]
+            .observe(started_at.elapsed().as_secs_f64());
+
+        submission_result(resp.status(), Method::SubmitFailedFri, || {
+            format!("failed FRI proof of batch {batch_number}")
+        })
     }
 
-    async fn pick_snark_job(&self) -> anyhow::Result<Option<SnarkProofInputs>> {
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn pick_snark_job(&self) -> Result<Option<SnarkProofInputs>, ProofClientError> {
         let url = self.build_url(&format!("SNARK/pick?{}", self.pick_query()))?;
 
         let started_at = Instant::now();
 
-        let resp = self
-            .client
-            .post(url.clone())
-            .send()
-            .await
-            .context("Pick Snark Job request failed")?;
+        let resp = self.pick_request(url).send().await?;
 
         SEQUENCER_CLIENT_METRICS.time_taken[&Method::PickSnark]
             .observe(started_at.elapsed().as_secs_f64());
 
         match resp.status() {
             StatusCode::OK => {
-                let get_snark_proof_payload = resp.json::<GetSnarkProofPayload>().await?;
-                Ok(Some(
-                    get_snark_proof_payload
-                        .try_into()
-                        .context("failed to parse SnarkProofPayload")?,
-                ))
+                let (job, digest) = receive_snark_job(resp, Method::PickSnark).await?;
+                tracing::info!(
+                    "Picked SNARK job for batches {}..={} from {}, job body sha-256 {digest}",
+                    job.from_batch_number,
+                    job.to_batch_number,
+                    self.endpoint
+                );
+                Ok(Some(job))
             }
             StatusCode::NO_CONTENT => Ok(None),
-            s => Err(anyhow!("Failed to pick SNARK job: status {s} from {url}")),
+            s => Err(ProofClientError::from_status(s)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
     async fn submit_snark_proof(
         &self,
         from_batch_number: L2BatchNumber,
         to_batch_number: L2BatchNumber,
         vk_hash: String,
         proof: SnarkWrapperProof,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), ProofClientError> {
         let url = self.build_url(&format!("SNARK/submit?id={}", self.prover_name))?;
 
         let started_at = Instant::now();
 
-        let serialized_proof = self
-            .serialize_snark_proof(&proof)
-            .context("Failed to serialize SNARK proof")?;
-
-        let payload = SubmitSnarkProofPayload {
-            from_batch_number: from_batch_number.0 as u64,
-            to_batch_number: to_batch_number.0 as u64,
-            vk_hash,
-            proof: serialized_proof,
+        let proof_bytes = crate::snark_proof_bytes(&proof);
+        tracing::info!(
+            "Submitting SNARK proof for batches {from_batch_number}..={to_batch_number} to {}, \
+             proof sha-256 {}",
+            self.endpoint,
+            payload_digest(&proof_bytes)
+        );
+        let idempotency_key = submission_idempotency_key(
+            from_batch_number.0 as u64,
+            to_batch_number.0 as u64,
+            &vk_hash,
+            &proof_bytes,
+        );
+        let body = if self.binary_payloads() {
+            let header = SnarkJobHeader {
+                from_batch_number: from_batch_number.0 as u64,
+                to_batch_number: to_batch_number.0 as u64,
+                vk_hash,
+            };
+            Body::Binary(encode_frames(&header, &[&proof_bytes]))
+        } else {
+            Body::json(&SubmitSnarkProofPayload {
+                from_batch_number: from_batch_number.0 as u64,
+                to_batch_number: to_batch_number.0 as u64,
+                vk_hash,
+                proof: STANDARD.encode(&proof_bytes),
+            })
         };
-        self.client
-            .post(url.clone())
-            .json(&payload)
+        let resp = self
+            .with_body(self.post(url), Method::SubmitSnark, body)?
+            .header(IDEMPOTENCY_KEY, idempotency_key)
             .send()
-            .await
-            .context("Submit Snark Proof request failed")?
-            .error_for_status()
-            .context("Request returned error status")?;
+            .await?;
+        submission_result(resp.status(), Method::SubmitSnark, || {
+            format!("SNARK proof of batches {from_batch_number}..={to_batch_number}")
+        })?;
 
         SEQUENCER_CLIENT_METRICS.time_taken[&Method::SubmitSnark]
             .observe(started_at.elapsed().as_secs_f64());
         Ok(())
     }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn heartbeat_fri_job(&self, batch_number: u32) -> Result<bool, ProofClientError> {
+        if !self.may_use(SequencerFeature::Heartbeats) {
+            return Ok(false);
+        }
+        let url = self.build_url(&format!(
+            "FRI/{batch_number}/heartbeat?id={}",
+            self.prover_name
+        ))?;
+        let resp = self.post(url).send().await?;
+        optional_route_result(resp.status())
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn heartbeat_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+    ) -> Result<bool, ProofClientError> {
+        if !self.may_use(SequencerFeature::Heartbeats) {
+            return Ok(false);
+        }
+        let url = self.build_url(&format!(
+            "SNARK/{from_batch_number}/{to_batch_number}/heartbeat?id={}",
+            self.prover_name
+        ))?;
+        let resp = self.post(url).send().await?;
+        optional_route_result(resp.status())
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn release_fri_job(
+        &self,
+        batch_number: u32,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        if !self.may_use(SequencerFeature::Release) {
+            return Ok(false);
+        }
+        let url = self.build_url(&format!(
+            "FRI/{batch_number}/release?id={}",
+            self.prover_name
+        ))?;
+        let payload = ReleaseJobPayload { reason, message };
+        let resp = self.post(url).json(&payload).send().await?;
+        optional_route_result(resp.status())
+    }
+
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn release_snark_job(
+        &self,
+        from_batch_number: L2BatchNumber,
+        to_batch_number: L2BatchNumber,
+        reason: ReleaseReason,
+        message: String,
+    ) -> Result<bool, ProofClientError> {
+        if !self.may_use(SequencerFeature::Release) {
+            return Ok(false);
+        }
+        let url = self.build_url(&format!(
+            "SNARK/{from_batch_number}/{to_batch_number}/release?id={}",
+            self.prover_name
+        ))?;
+        let payload = ReleaseJobPayload { reason, message };
+        let resp = self.post(url).json(&payload).send().await?;
+        optional_route_result(resp.status())
+    }
+}
+
+/// Outcome of a request to a route older sequencers lack (heartbeats, releases): they
+/// answer `404`, `405` or `501`, which means "not supported" rather than an error.
+fn optional_route_result(status: StatusCode) -> Result<bool, ProofClientError> {
+    match status {
+        s if s.is_success() => Ok(true),
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {
+            Ok(false)
+        }
+        s => Err(ProofClientError::from_status(s)),
+    }
 }
 
 #[async_trait]
 impl PeekableProofClient for SequencerProofClient {
-    async fn peek_fri_job(&self, batch_number: u32) -> anyhow::Result<Option<(u32, Vec<u8>)>> {
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
+    async fn peek_fri_job(
+        &self,
+        batch_number: u32,
+    ) -> Result<Option<FriJobInputs>, ProofClientError> {
         let url = self.build_url(&format!("FRI/{batch_number}/peek"))?;
-        let resp = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .context("Peek Fri Job request failed")?;
+        let resp = self.get(url).timeout(self.timeouts.peek).send().await?;
 
         match resp.status() {
             StatusCode::OK => {
-                let body: NextFriProverJobPayload = resp.json().await?;
-                let data = STANDARD
-                    .decode(&body.prover_input)
-                    .map_err(|e| anyhow!("Failed to decode batch data: {e}"))?;
-                Ok(Some((body.batch_number, data)))
+                let announced = announced_digest(resp.headers())?;
+                let body = resp.bytes().await?;
+                verify_digest(announced.as_deref(), Sha256::digest(&body))?;
+                Ok(Some(
+                    parse_json::<NextFriProverJobPayload>(&body)?.try_into()?,
+                ))
             }
             StatusCode::NO_CONTENT => Ok(None),
-            s => Err(anyhow!(
-                "Unexpected status {s} when peeking the batch {batch_number} at {url}",
-            )),
+            s => Err(ProofClientError::from_status(s)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
     async fn peek_snark_job(
         &self,
         from_batch_number: u32,
         to_batch_number: u32,
-    ) -> anyhow::Result<Option<SnarkProofInputs>> {
+    ) -> Result<Option<SnarkProofInputs>, ProofClientError> {
         let url = self.build_url(&format!("SNARK/{from_batch_number}/{to_batch_number}/peek"))?;
-        let resp = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .context("Peek Snark Job request failed")?;
+        let resp = self.get(url).timeout(self.timeouts.peek).send().await?;
 
         match resp.status() {
-            StatusCode::OK => {
-                let get_snark_proof_payload = resp.json::<GetSnarkProofPayload>().await?;
-                Ok(Some(
-                    get_snark_proof_payload
-                        .try_into()
-                        .context("failed to parse SnarkProofPayload")?,
-                ))
-            }
+            StatusCode::OK => Ok(Some(receive_snark_job(resp, Method::PeekSnark).await?.0)),
             StatusCode::NO_CONTENT => Ok(None),
-            s => Err(anyhow!(
-                "Unexpected status {s} when peeking FRI proofs from {from_batch_number} to {to_batch_number} at {url}",
-            )),
+            s => Err(ProofClientError::from_status(s)),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(sequencer = %self.endpoint))]
     async fn get_failed_fri_proof(
         &self,
         batch_number: u32,
-    ) -> anyhow::Result<Option<FailedFriProofPayload>> {
+    ) -> Result<Option<FailedFriProofPayload>, ProofClientError> {
         let url = self.build_url(&format!("FRI/{batch_number}/failed"))?;
-        let resp = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .context("Get Failed Fri Proof request failed")?;
+        let resp = self.get(url).timeout(self.timeouts.peek).send().await?;
 
         match resp.status() {
             StatusCode::OK => {
-                let body: FailedFriProofPayload = resp.json().await?;
-                Ok(Some(body))
+                let announced = announced_digest(resp.headers())?;
+                let body = resp.bytes().await?;
+                let digest = Sha256::digest(&body);
+                verify_digest(announced.as_deref(), digest)?;
+                tracing::info!(
+                    "Fetched failed FRI proof for batch {batch_number} from {}, body sha-256 {digest:x}",
+                    self.endpoint
+                );
+                Ok(Some(parse_json(&body)?))
             }
             StatusCode::NO_CONTENT => Ok(None),
-            s => Err(anyhow!(
-                "Unexpected status {s} when peeking failed FRI proof for batch {batch_number} at {url}",
-            )),
+            s => Err(ProofClientError::from_status(s)),
         }
     }
 }
@@ -0,0 +1,389 @@
+//! Decoding of SNARK jobs as their bodies stream in.
+//!
+//! A SNARK job carries up to a hundred FRI proofs. Instead of buffering the body and then
+//! decoding proof after proof on the async runtime, the body is handed chunk by chunk to a
+//! blocking task, which parses `fri_proofs` one element at a time and bincode-decodes them
+//! in parallel batches on the rayon pool. Besides the decoded proofs, a job holds at most
+//! [`CHUNK_BUFFER`] body chunks and one batch of raw proofs at a time.
+
+use std::fmt;
+use std::io::Read;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rayon::prelude::*;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use sha2::{Digest as _, Sha256};
+use tokio::sync::mpsc;
+use zksync_airbender_execution_utils::unrolled::UnrolledProgramProof;
+
+use crate::metrics::{observe_payload_size, Method};
+use crate::transport::{announced_digest, body_format, read_frame, verify_digest};
+use crate::{ProofClientError, SnarkJobHeader, SnarkProofInputs};
+
+/// Body chunks buffered between the connection and the decoder.
+const CHUNK_BUFFER: usize = 16;
+
+/// Bincode-decodes FRI proofs in parallel batches, keeping their order.
+#[derive(Default)]
+pub(crate) struct FriProofDecoder {
+    /// Raw proofs waiting for the next batch, with their index in the job.
+    pending: Vec<(usize, Vec<u8>)>,
+    decoded: Vec<UnrolledProgramProof>,
+    next_index: usize,
+}
+
+impl FriProofDecoder {
+    /// Index the next pushed proof will have in the job.
+    pub(crate) fn next_index(&self) -> usize {
+        self.next_index
+    }
+
+    pub(crate) fn push(&mut self, proof: Vec<u8>) -> Result<(), ProofClientError> {
+        self.pending.push((self.next_index, proof));
+        self.next_index += 1;
+        if self.pending.len() >= 2 * rayon::current_num_threads() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ProofClientError> {
+        let results: Vec<_> = self
+            .pending
+            .par_drain(..)
+            .map(|(i, bytes)| {
+                bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+                    .map(|(proof, _)| proof)
+                    .map_err(|e| ProofClientError::MalformedPayload(format!("FRI proof {i}: {e}")))
+            })
+            .collect();
+        for result in results {
+            self.decoded.push(result?);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<Vec<UnrolledProgramProof>, ProofClientError> {
+        self.flush()?;
+        Ok(self.decoded)
+    }
+}
+
+/// Receive a SNARK job body (JSON or binary frames, possibly compressed) and decode it
+/// while it streams in. The job is only returned once the whole body matched its
+/// `Content-Digest`; the hex SHA-256 of the body comes with it.
+pub(crate) async fn receive_snark_job(
+    mut resp: reqwest::Response,
+    method: Method,
+) -> Result<(SnarkProofInputs, String), ProofClientError> {
+    let (binary, encoding) = body_format(resp.headers())?;
+    let announced = announced_digest(resp.headers())?;
+    let (sender, receiver) = mpsc::channel(CHUNK_BUFFER);
+    let decoding = tokio::task::spawn_blocking(move || {
+        let mut reader = CountingReader {
+            inner: encoding.reader(ChunkReader::new(receiver))?,
+            count: 0,
+        };
+        let job = if binary {
+            decode_binary(&mut reader)
+        } else {
+            decode_json(&mut reader)
+        };
+        Ok::<_, std::io::Error>((job, reader.count))
+    });
+
+    let mut wire = 0;
+    let mut digest = Sha256::new();
+    let mut decoder_gave_up = false;
+    loop {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => {
+                wire += chunk.len();
+                digest.update(&chunk);
+                if decoder_gave_up {
+                    continue;
+                }
+                if sender.send(Ok(Vec::from(chunk))).await.is_err() {
+                    // The decoder gave up on the body; its error is reported below, unless
+                    // the rest of the body shows it was corrupted.
+                    if announced.is_none() {
+                        break;
+                    }
+                    decoder_gave_up = true;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                sender
+                    .send(Err(std::io::Error::other("body interrupted")))
+                    .await
+                    .ok();
+                return Err(e.into());
+            }
+        }
+    }
+    drop(sender);
+    let digest = digest.finalize();
+    // A corrupted body most likely fails to decode too; the mismatch is the error to report.
+    verify_digest(announced.as_deref(), digest)?;
+
+    let (job, decoded) = decoding
+        .await
+        .map_err(|e| ProofClientError::MalformedPayload(format!("SNARK job decoder failed: {e}")))?
+        .map_err(|e| {
+            ProofClientError::MalformedPayload(format!("Failed to decompress payload: {e}"))
+        })?;
+    observe_payload_size(method, decoded, wire);
+    Ok((job?, format!("{digest:x}")))
+}
+
+/// Parse a JSON SNARK job (`GetSnarkProofPayload`), decoding each `fri_proofs` element
+/// as soon as it is parsed.
+fn decode_json(reader: impl Read) -> Result<SnarkProofInputs, ProofClientError> {
+    let mut decoder = FriProofDecoder::default();
+    let mut error = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let header = JsonSnarkJob {
+        decoder: &mut decoder,
+        error: &mut error,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|header| deserializer.end().map(|()| header));
+    let header = match (header, error) {
+        (_, Some(error)) => return Err(error),
+        (Ok(header), None) => header,
+        (Err(e), None) => {
+            return Err(ProofClientError::MalformedPayload(format!(
+                "Failed to parse SNARK job: {e}"
+            )))
+        }
+    };
+    header.into_inputs(decoder.finish()?)
+}
+
+/// Parse a binary SNARK job: a [`SnarkJobHeader`] frame, then one frame per FRI proof.
+fn decode_binary(mut reader: impl Read) -> Result<SnarkProofInputs, ProofClientError> {
+    let malformed = |e| ProofClientError::MalformedPayload(format!("malformed binary body: {e}"));
+    let header = read_frame(&mut reader).map_err(malformed)?.ok_or_else(|| {
+        ProofClientError::MalformedPayload("binary body has no header frame".to_string())
+    })?;
+    let header: SnarkJobHeader = serde_json::from_slice(&header).map_err(|e| {
+        ProofClientError::MalformedPayload(format!("malformed binary payload header: {e}"))
+    })?;
+
+    let mut decoder = FriProofDecoder::default();
+    while let Some(proof) = read_frame(&mut reader).map_err(|e| {
+        ProofClientError::MalformedPayload(format!("FRI proof {}: {e}", decoder.next_index()))
+    })? {
+        decoder.push(proof)?;
+    }
+    header.into_inputs(decoder.finish()?)
+}
+
+/// Deserializes a JSON SNARK job into its [`SnarkJobHeader`], feeding `fri_proofs` to
+/// `decoder` on the way. A decoding error is left in `error` and aborts parsing.
+struct JsonSnarkJob<'a> {
+    decoder: &'a mut FriProofDecoder,
+    error: &'a mut Option<ProofClientError>,
+}
+
+impl<'de> DeserializeSeed<'de> for JsonSnarkJob<'_> {
+    type Value = SnarkJobHeader;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonSnarkJob<'_> {
+    type Value = SnarkJobHeader;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a SNARK job")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut from_batch_number, mut to_batch_number, mut vk_hash) = (None, None, None);
+        let mut fri_proofs = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "from_batch_number" => from_batch_number = Some(map.next_value()?),
+                "to_batch_number" => to_batch_number = Some(map.next_value()?),
+                "vk_hash" => vk_hash = Some(map.next_value()?),
+                "fri_proofs" => {
+                    map.next_value_seed(FriProofs {
+                        decoder: &mut *self.decoder,
+                        error: &mut *self.error,
+                    })?;
+                    fri_proofs = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if !fri_proofs {
+            return Err(de::Error::missing_field("fri_proofs"));
+        }
+        Ok(SnarkJobHeader {
+            from_batch_number: from_batch_number
+                .ok_or_else(|| de::Error::missing_field("from_batch_number"))?,
+            to_batch_number: to_batch_number
+                .ok_or_else(|| de::Error::missing_field("to_batch_number"))?,
+            vk_hash: vk_hash.ok_or_else(|| de::Error::missing_field("vk_hash"))?,
+        })
+    }
+}
+
+/// The `fri_proofs` list of a JSON SNARK job: base64 strings, decoded one at a time.
+struct FriProofs<'a> {
+    decoder: &'a mut FriProofDecoder,
+    error: &'a mut Option<ProofClientError>,
+}
+
+impl<'de> DeserializeSeed<'de> for FriProofs<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FriProofs<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of base64-encoded FRI proofs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(encoded) = seq.next_element::<String>()? {
+            let i = self.decoder.next_index();
+            let pushed = STANDARD
+                .decode(encoded)
+                .map_err(|e| {
+                    ProofClientError::MalformedPayload(format!("FRI proof {i} is not base64: {e}"))
+                })
+                .and_then(|proof| self.decoder.push(proof));
+            if let Err(e) = pushed {
+                let message = e.to_string();
+                *self.error = Some(e);
+                return Err(de::Error::custom(message));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads body chunks sent by [`receive_snark_job`].
+struct ChunkReader {
+    receiver: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChunkReader {
+    fn new(receiver: mpsc::Receiver<std::io::Result<Vec<u8>>>) -> Self {
+        Self {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Counts the (decompressed) bytes read through it.
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_job_errors_carry_the_proof_index() {
+        let job = r#"{"from_batch_number":1,"to_batch_number":2,"vk_hash":"0x","fri_proofs":["AAAA","!!"]}"#;
+        let err = decode_json(job.as_bytes()).unwrap_err();
+        assert!(
+            err.to_string().contains("FRI proof 1 is not base64"),
+            "{err}"
+        );
+
+        let job = r#"{"from_batch_number":1,"to_batch_number":2,"vk_hash":"0x","fri_proofs":[""]}"#;
+        let err = decode_json(job.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("FRI proof 0:"), "{err}");
+    }
+
+    #[test]
+    fn test_json_job_fields_are_checked() {
+        let job = decode_json(
+            r#"{"vk_hash":"0x","fri_proofs":[],"extra":{"a":[1]},"to_batch_number":5,"from_batch_number":3}"#
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!((job.from_batch_number.0, job.to_batch_number.0), (3, 5));
+        assert!(job.fri_proofs.is_empty());
+
+        let err = decode_json(r#"{"from_batch_number":1,"fri_proofs":[]}"#.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("missing field"), "{err}");
+
+        let err = decode_json(
+            r#"{"from_batch_number":4294967296,"to_batch_number":5,"vk_hash":"0x","fri_proofs":[]}"#
+                .as_bytes(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, ProofClientError::MalformedPayload(_)),
+            "{err}"
+        );
+        assert!(
+            err.to_string().contains("from_batch_number 4294967296"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_chunk_reader_reassembles_the_body() {
+        let (sender, receiver) = mpsc::channel(4);
+        for chunk in [&b"ab"[..], b"", b"cde"] {
+            sender.try_send(Ok(chunk.to_vec())).unwrap();
+        }
+        drop(sender);
+        let mut body = String::new();
+        ChunkReader::new(receiver)
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "abcde");
+    }
+}
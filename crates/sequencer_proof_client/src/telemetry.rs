@@ -0,0 +1,120 @@
+//! Tracing setup shared by the prover binaries, and W3C trace context propagation.
+//!
+//! Spans are recorded through OpenTelemetry even without an exporter, so every request
+//! [`crate::SequencerProofClient`] makes carries a `traceparent` header naming the span it
+//! was made in (the job being proven, or the pick). With `--otlp-endpoint`, the spans are
+//! also exported to an OTLP collector over HTTP.
+
+use anyhow::Context as _;
+use opentelemetry::propagation::{Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig as _};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+
+/// Command-line options of tracing, shared by the prover binaries.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct TelemetryArgs {
+    /// OTLP/HTTP endpoint to export job and request spans to, e.g.
+    /// `http://localhost:4318/v1/traces`. Without it, spans only show up in logs.
+    #[arg(long, global = true)]
+    pub otlp_endpoint: Option<Url>,
+}
+
+/// Flushes exported spans on [`TelemetryGuard::shutdown`], or when dropped; keep it alive
+/// until the process exits.
+#[must_use = "spans are only flushed while the guard is alive"]
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    /// Flush the spans not exported yet and stop exporting. Flushing blocks until the
+    /// collector answers (or times out), so it runs on a blocking thread rather than holding
+    /// up the runtime as dropping the guard inside it would.
+    pub async fn shutdown(mut self) {
+        let Some(provider) = self.provider.take() else {
+            return;
+        };
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to flush spans: {e}"),
+            Err(e) => tracing::warn!("Flushing spans panicked: {e}"),
+        }
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!("Failed to flush spans: {e}");
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber: logs filtered by `RUST_LOG` (`default_filter`
+/// if unset), and spans recorded as `service_name` for trace propagation and, if
+/// configured, OTLP export.
+pub fn init_tracing(
+    service_name: &'static str,
+    default_filter: &str,
+    args: &TelemetryArgs,
+) -> anyhow::Result<TelemetryGuard> {
+    let mut provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(service_name).build());
+    if let Some(endpoint) = &args.otlp_endpoint {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint.as_str())
+            .build()
+            .context("Failed to create OTLP span exporter")?;
+        provider = provider.with_batch_exporter(exporter);
+    }
+    let provider = provider.build();
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(service_name)))
+        .try_init()
+        .context("Failed to install tracing subscriber")?;
+    if let Some(endpoint) = &args.otlp_endpoint {
+        tracing::info!("Exporting spans to {endpoint}");
+    }
+    Ok(TelemetryGuard {
+        provider: Some(provider),
+    })
+}
+
+/// `traceparent` (and `tracestate`) headers for a request made in the current span; empty
+/// if the span isn't recorded through OpenTelemetry.
+pub(crate) fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = tracing::Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(&mut headers));
+    headers
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+/// Timeout of requests to the sequencer when nothing else is configured.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+const MIB: f64 = (1 << 20) as f64;
+
+/// Timeouts of a [`crate::SequencerProofClient`]'s requests, by kind: a proof upload may
+/// take minutes while a pick that hangs that long stalls the prover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    /// Establishing a connection (TCP and TLS handshake).
+    pub connect: Duration,
+    /// Picks, and the other small requests: capabilities, heartbeats, releases, failure reports.
+    /// Long-polled picks get the long-poll wait on top.
+    pub pick: Duration,
+    /// Peeks, which download a job like picks do but aren't on the proving path.
+    pub peek: Duration,
+    /// Proof submissions, on top of [`Self::submit_per_mib`].
+    pub submit: Duration,
+    /// Extra time for every MiB of a submitted (compressed) body.
+    pub submit_per_mib: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self::uniform(DEFAULT_REQUEST_TIMEOUT)
+    }
+}
+
+impl RequestTimeouts {
+    /// The same timeout for every request, regardless of its size.
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            connect: timeout,
+            pick: timeout,
+            peek: timeout,
+            submit: timeout,
+            submit_per_mib: Duration::ZERO,
+        }
+    }
+
+    /// Timeout of a submission with a `body_len`-byte body.
+    pub fn submit(&self, body_len: usize) -> Duration {
+        self.submit + self.submit_per_mib.mul_f64(body_len as f64 / MIB)
+    }
+}
+
+/// Command-line options of sequencer request timeouts, shared by the prover binaries.
+/// Each unset timeout falls back to the binary's `--request-timeout-secs`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct TimeoutArgs {
+    /// Timeout for connecting to a sequencer, in seconds [default: --request-timeout-secs]
+    #[arg(long)]
+    pub connect_timeout_secs: Option<u64>,
+    /// Timeout for picking a job (and other small requests), in seconds
+    /// [default: --request-timeout-secs]
+    #[arg(long)]
+    pub pick_timeout_secs: Option<u64>,
+    /// Timeout for peeking at a job, in seconds [default: --request-timeout-secs]
+    #[arg(long)]
+    pub peek_timeout_secs: Option<u64>,
+    /// Base timeout for submitting a proof, in seconds [default: --request-timeout-secs]
+    #[arg(long)]
+    pub submit_timeout_secs: Option<u64>,
+    /// Extra submit timeout per MiB of proof body, in milliseconds
+    #[arg(long, default_value_t = 0)]
+    pub submit_timeout_per_mib_ms: u64,
+}
+
+impl TimeoutArgs {
+    /// The configured timeouts, with `request_timeout` for those left unset.
+    pub fn timeouts(&self, request_timeout: Duration) -> RequestTimeouts {
+        let or_default = |secs: Option<u64>| secs.map_or(request_timeout, Duration::from_secs);
+        RequestTimeouts {
+            connect: or_default(self.connect_timeout_secs),
+            pick: or_default(self.pick_timeout_secs),
+            peek: or_default(self.peek_timeout_secs),
+            submit: or_default(self.submit_timeout_secs),
+            submit_per_mib: Duration::from_millis(self.submit_timeout_per_mib_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submit_timeout_scales_with_body_size() {
+        let timeouts = RequestTimeouts {
+            submit: Duration::from_secs(10),
+            submit_per_mib: Duration::from_secs(2),
+            ..RequestTimeouts::default()
+        };
+        assert_eq!(timeouts.submit(0), Duration::from_secs(10));
+        assert_eq!(timeouts.submit(1 << 20), Duration::from_secs(12));
+        assert_eq!(timeouts.submit(5 << 19), Duration::from_secs(15));
+
+        // Without a per-MiB allowance, every submission gets the same timeout.
+        let uniform = RequestTimeouts::uniform(Duration::from_secs(300));
+        assert_eq!(uniform.submit(100 << 20), Duration::from_secs(300));
+    }
+}
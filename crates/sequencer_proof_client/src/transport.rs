@@ -0,0 +1,267 @@
+//! Binary transport of prover inputs and proofs, for sequencers advertising
+//! [`crate::SequencerFeature::BinaryPayloads`].
+//!
+//! A binary body (`Content-Type: application/octet-stream`) is a sequence of frames, each a
+//! big-endian `u32` length followed by that many bytes. The first frame is the JSON payload
+//! without its base64 fields; every following frame is one of those fields as raw bytes, in
+//! order: the prover input of a FRI job, the FRI proofs of a SNARK job, or the proof of a
+//! submission. The body may be compressed as announced in `Content-Encoding`.
+//!
+//! Job responses and proof submissions, binary or JSON, carry the SHA-256 of their body as
+//! sent (after compression) in a `Content-Digest` header (RFC 9530, `sha-256=:<base64>:`).
+
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::header::{HeaderMap, CONTENT_ENCODING, CONTENT_TYPE};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::ProofClientError;
+
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const CONTENT_DIGEST: &str = "content-digest";
+
+/// Compression of a payload body, as named in `Content-Encoding` / `Accept-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+}
+
+impl PayloadEncoding {
+    /// Encodings this client can decode, preferred first.
+    pub const SUPPORTED: &[Self] = &[Self::Zstd, Self::Gzip];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Parse a `Content-Encoding` / `Accept-Encoding` token.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "identity" => Some(Self::Identity),
+            "gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn compress(self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(data),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::encode_all(data.as_slice(), 0),
+        }
+    }
+
+    /// Wrap `reader` to decompress what it reads.
+    pub fn reader<'a>(self, reader: impl Read + 'a) -> std::io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Identity => Box::new(reader),
+            Self::Gzip => Box::new(flate2::read::GzDecoder::new(reader)),
+            Self::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+
+    pub fn decompress(self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(data),
+            Self::Gzip => {
+                let mut decoded = Vec::new();
+                flate2::read::GzDecoder::new(data.as_slice()).read_to_end(&mut decoded)?;
+                Ok(decoded)
+            }
+            Self::Zstd => zstd::decode_all(data.as_slice()),
+        }
+    }
+}
+
+/// Frame `header` (as JSON) and `blobs` into a binary body.
+pub(crate) fn encode_frames<H: Serialize>(header: &H, blobs: &[&[u8]]) -> Vec<u8> {
+    let header = serde_json::to_vec(header).expect("payload headers serialize to JSON");
+    let len = 4 * (blobs.len() + 1) + header.len() + blobs.iter().map(|b| b.len()).sum::<usize>();
+    let mut body = Vec::with_capacity(len);
+    for frame in std::iter::once(header.as_slice()).chain(blobs.iter().copied()) {
+        let frame_len = u32::try_from(frame.len()).expect("payload frames are below 4 GiB");
+        body.extend_from_slice(&frame_len.to_be_bytes());
+        body.extend_from_slice(frame);
+    }
+    body
+}
+
+/// Read the next frame, or `None` at the end of the body.
+pub(crate) fn read_frame(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    if reader.read(&mut len[..1])? == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut len[1..])?;
+    let len = u32::from_be_bytes(len) as u64;
+    // Not preallocated: `len` comes from the sequencer.
+    let mut frame = Vec::new();
+    reader.take(len).read_to_end(&mut frame)?;
+    if frame.len() as u64 != len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("frame of {len} bytes is truncated to {}", frame.len()),
+        ));
+    }
+    Ok(Some(frame))
+}
+
+/// Split a binary body into its JSON header and the blobs after it.
+pub(crate) fn decode_frames<H: DeserializeOwned>(
+    mut body: &[u8],
+) -> Result<(H, Vec<Vec<u8>>), ProofClientError> {
+    let malformed = |e| ProofClientError::MalformedPayload(format!("malformed binary body: {e}"));
+    let header = read_frame(&mut body).map_err(malformed)?.ok_or_else(|| {
+        ProofClientError::MalformedPayload("binary body has no header frame".to_string())
+    })?;
+    let header = serde_json::from_slice(&header).map_err(|e| {
+        ProofClientError::MalformedPayload(format!("malformed binary payload header: {e}"))
+    })?;
+    let mut blobs = Vec::new();
+    while let Some(blob) = read_frame(&mut body).map_err(malformed)? {
+        blobs.push(blob);
+    }
+    Ok((header, blobs))
+}
+
+/// Whether a response body is binary, and its `Content-Encoding`.
+pub(crate) fn body_format(
+    headers: &HeaderMap,
+) -> Result<(bool, PayloadEncoding), ProofClientError> {
+    let binary = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(BINARY_CONTENT_TYPE));
+    let encoding = match headers.get(CONTENT_ENCODING) {
+        None => PayloadEncoding::Identity,
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(PayloadEncoding::parse)
+            .ok_or_else(|| {
+                ProofClientError::MalformedPayload(format!(
+                    "Unsupported content encoding {value:?}"
+                ))
+            })?,
+    };
+    Ok((binary, encoding))
+}
+
+/// `Content-Digest` value of a body.
+pub fn content_digest(body: &[u8]) -> String {
+    format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// The SHA-256 a response announces in `Content-Digest`, if any. Other algorithms are
+/// ignored, so a sequencer sending only those is trusted like one sending no digest.
+pub(crate) fn announced_digest(headers: &HeaderMap) -> Result<Option<String>, ProofClientError> {
+    let Some(value) = headers.get(CONTENT_DIGEST) else {
+        return Ok(None);
+    };
+    let malformed =
+        || ProofClientError::MalformedPayload(format!("malformed Content-Digest {value:?}"));
+    let value = value.to_str().map_err(|_| malformed())?;
+    for member in value.split(',') {
+        let Some((algorithm, digest)) = member.trim().split_once('=') else {
+            return Err(malformed());
+        };
+        if algorithm.trim().eq_ignore_ascii_case("sha-256") {
+            let digest = digest
+                .trim()
+                .strip_prefix(':')
+                .and_then(|digest| digest.strip_suffix(':'))
+                .ok_or_else(malformed)?;
+            return Ok(Some(digest.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Check a received body against its announced digest (see [`announced_digest`]).
+pub(crate) fn verify_digest(
+    announced: Option<&str>,
+    actual: impl AsRef<[u8]>,
+) -> Result<(), ProofClientError> {
+    let Some(expected) = announced else {
+        return Ok(());
+    };
+    let actual = STANDARD.encode(actual);
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(ProofClientError::DigestMismatch {
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_round_trip_through_every_encoding() {
+        let header = serde_json::json!({ "batch_number": 7 });
+        let blobs: [&[u8]; 3] = [b"prover input", b"", &[0; 4096]];
+        for encoding in [
+            PayloadEncoding::Identity,
+            PayloadEncoding::Gzip,
+            PayloadEncoding::Zstd,
+        ] {
+            let body = encoding.compress(encode_frames(&header, &blobs)).unwrap();
+            let decoded = encoding.decompress(body).unwrap();
+            let (decoded_header, decoded_blobs): (serde_json::Value, _) =
+                decode_frames(&decoded).unwrap();
+            assert_eq!(decoded_header, header);
+            assert_eq!(decoded_blobs, blobs.map(<[u8]>::to_vec));
+        }
+    }
+
+    #[test]
+    fn test_truncated_frames_are_rejected() {
+        let body = encode_frames(&serde_json::json!({}), &[b"proof"]);
+        let err = decode_frames::<serde_json::Value>(&body[..body.len() - 1]).unwrap_err();
+        assert!(matches!(err, ProofClientError::MalformedPayload(_)));
+        assert_eq!(PayloadEncoding::parse("br"), None);
+    }
+
+    #[test]
+    fn test_content_digests_are_verified() {
+        let body = b"{\"batch_number\":7}";
+        let mut headers = HeaderMap::new();
+        assert_eq!(announced_digest(&headers).unwrap(), None);
+
+        headers.insert(
+            CONTENT_DIGEST,
+            format!("sha-512=:AAAA:, {}", content_digest(body))
+                .parse()
+                .unwrap(),
+        );
+        let announced = announced_digest(&headers).unwrap();
+        verify_digest(announced.as_deref(), Sha256::digest(body)).unwrap();
+        let err = verify_digest(announced.as_deref(), Sha256::digest(b"{}")).unwrap_err();
+        assert!(
+            matches!(err, ProofClientError::DigestMismatch { .. }),
+            "{err}"
+        );
+
+        headers.insert(CONTENT_DIGEST, "sha-256=AAAA".parse().unwrap());
+        assert!(announced_digest(&headers).is_err());
+    }
+}
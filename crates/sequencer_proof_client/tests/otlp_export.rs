@@ -0,0 +1,76 @@
+//! Exports spans through `init_tracing` to a local OTLP/HTTP collector. Installing the global
+//! subscriber is once per process, hence a test binary of its own.
+
+use std::io::{BufRead as _, BufReader, Read as _, Write as _};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use url::Url;
+use zksync_sequencer_proof_client::{init_tracing, TelemetryArgs};
+
+/// A collector answering every request with 200, sending each request's head and body back.
+fn collector() -> (Url, mpsc::Receiver<(String, Vec<u8>)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut head = String::new();
+            loop {
+                let start = head.len();
+                reader.read_line(&mut head).unwrap();
+                if head[start..].trim_end().is_empty() {
+                    break;
+                }
+            }
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            if sender.send((head, body)).is_err() {
+                return;
+            }
+        }
+    });
+    (Url::parse(&endpoint).unwrap(), receiver)
+}
+
+#[tokio::test]
+async fn spans_are_exported_over_otlp() {
+    let (endpoint, requests) = collector();
+    let telemetry = init_tracing(
+        "otlp_export_test",
+        "info",
+        &TelemetryArgs {
+            otlp_endpoint: Some(endpoint),
+        },
+    )
+    .unwrap();
+
+    tracing::info_span!("prove_batch_17").in_scope(|| tracing::info!("proving"));
+    telemetry.shutdown().await;
+
+    let (head, body) = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(head.starts_with("POST /v1/traces "), "{head}");
+    assert!(
+        head.to_ascii_lowercase()
+            .contains("content-type: application/x-protobuf"),
+        "{head}"
+    );
+    // Protobuf carries strings as-is.
+    let contains = |needle: &[u8]| body.windows(needle.len()).any(|window| window == needle);
+    assert!(contains(b"prove_batch_17"));
+    assert!(contains(b"otlp_export_test"));
+}
@@ -2,12 +2,24 @@ use std::time::Duration;
 
 use clap::Parser;
 use tokio::sync::watch;
-use zksync_os_fri_prover::{init_tracing, metrics};
+use zksync_os_fri_prover::{init_tracing, metrics, prove, Command};
 
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
-    init_tracing();
     let args = zksync_os_fri_prover::Args::parse();
+    let telemetry = init_tracing(&args.telemetry)?;
+    let result = run(args).await;
+    telemetry.shutdown().await;
+    result
+}
+
+async fn run(mut args: zksync_os_fri_prover::Args) -> anyhow::Result<()> {
+    if let Some(Command::Prove(prove_args)) = args.command.take() {
+        // Proving takes minutes of synchronous work; keep it off the runtime's workers.
+        return tokio::task::spawn_blocking(move || prove::prove_locally(prove_args))
+            .await
+            .expect("FRI prove task panicked");
+    }
 
     let (stop_sender, stop_receiver) = watch::channel(false);
 
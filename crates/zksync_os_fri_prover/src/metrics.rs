@@ -1,8 +1,9 @@
 use std::net::Ipv4Addr;
 
 use tokio::sync::watch;
-use vise::{Counter, Gauge, Histogram, Metrics, MetricsCollection};
+use vise::{Counter, Family, Gauge, Histogram, Metrics, MetricsCollection};
 use vise_exporter::MetricsExporter;
+use zksync_sequencer_proof_client::ProofClientErrorKind;
 
 pub async fn start_metrics_exporter(
     port: u16,
@@ -37,6 +38,12 @@ pub struct FriProverMetrics {
     pub latest_proven_batch: Gauge,
     /// Number of timeout errors when communicating with sequencer
     pub timeout_errors: Counter,
+    /// Number of failed sequencer calls, by error kind
+    pub client_errors: Family<ProofClientErrorKind, Counter>,
+    /// Number of proofs not matching their batch's expected hash, reported as failed
+    pub failed_proofs: Counter,
+    /// Number of picked jobs released back to the sequencer without proving them
+    pub rejected_jobs: Counter,
 }
 
 #[vise::register]
@@ -0,0 +1,238 @@
+//! Proving a single job from a file, without a sequencer: for reproducing a slow or failing
+//! batch on a prover host.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use protocol_version::SupportedProtocolVersions;
+use serde::Serialize;
+use zksync_sequencer_proof_client::{
+    payload_digest, Envelope, EnvelopeFormat, Enveloped, FriJobInputs, FriProofFile,
+};
+
+use crate::{
+    app_bin_path, check_program, create_proof, create_prover, final_output_registers,
+    prover_input_words,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ProveArgs {
+    /// FRI job envelope to prove (e.g. saved by `zksync_sequencer_proof_client pick-fri`), or a
+    /// raw prover input: little-endian words, or a JSON array of bytes
+    #[arg(short, long)]
+    pub input: PathBuf,
+    /// Batch number of a raw prover input [default: 0]; checked against a job envelope
+    #[arg(long)]
+    pub batch_number: Option<u32>,
+    /// vk hash the job is proven for, checked to prove the app program like a picked job's;
+    /// checked against a job envelope. Without it (and an envelope), any supported
+    /// version's program is accepted
+    #[arg(long)]
+    pub vk_hash: Option<String>,
+    /// Path to `app.bin`
+    #[arg(long)]
+    pub app_bin_path: Option<PathBuf>,
+    /// Directory to write `fri_proof_<batch>.<ext>` and `fri_proof_<batch>.meta.json` to
+    #[arg(long, default_value = ".")]
+    pub output_dir: PathBuf,
+    /// Format of the proof envelope
+    #[arg(long, value_enum, default_value_t = EnvelopeFormat::Json)]
+    pub format: EnvelopeFormat,
+}
+
+/// What was proven and how, written next to the proof.
+#[derive(Debug, Serialize)]
+struct ProofMetadata {
+    batch_number: u32,
+    vk_hash: Option<String>,
+    app_bin_path: PathBuf,
+    program_commitment: String,
+    security_level: String,
+    prover_input_bytes: usize,
+    prover_input_sha256: String,
+    proof_file: PathBuf,
+    proof_sha256: String,
+    proving_time_secs: f64,
+    /// Output registers 10..=25: the batch output hash, then the app program commitment.
+    final_output_registers: [u32; 16],
+    expected_hash_u32s: Option<[u32; 8]>,
+    /// Whether the proof outputs `expected_hash_u32s`, if the job carried it.
+    output_hash_matches: Option<bool>,
+}
+
+/// Prove the job at `args.input` and write the proof and its metadata. Fails if the proof
+/// doesn't output the job's expected hash, after writing both.
+pub fn prove_locally(args: ProveArgs) -> anyhow::Result<()> {
+    let data =
+        std::fs::read(&args.input).with_context(|| format!("Failed to read {:?}", args.input))?;
+    let job = parse_job(&data, args.batch_number, args.vk_hash)
+        .with_context(|| format!("Failed to load a job from {:?}", args.input))?;
+    let prover_input = prover_input_words(&job.prover_input).with_context(|| {
+        format!(
+            "prover input has {} bytes, expected a multiple of 4",
+            job.prover_input.len()
+        )
+    })?;
+
+    let supported_versions = SupportedProtocolVersions::default();
+    let binary_path = app_bin_path(args.app_bin_path);
+    let prover = create_prover(&binary_path)?;
+    let program_commitment = check_program(&prover, &binary_path, &supported_versions)?;
+    // Without a vk hash there's no version to hold the program to beyond `check_program`.
+    let vk_hash = (!job.vk_hash.is_empty()).then_some(job.vk_hash.clone());
+    if let Some(vk_hash) = &vk_hash {
+        anyhow::ensure!(
+            supported_versions.contains(vk_hash),
+            "unsupported protocol version with vk_hash {vk_hash}"
+        );
+        let expected = supported_versions.program_commitment_for(vk_hash);
+        anyhow::ensure!(
+            expected == Some(program_commitment),
+            "vk_hash {vk_hash} does not prove the app program {program_commitment} (version's \
+             commitment: {})",
+            expected.map_or_else(|| "none".to_string(), |c| c.to_string())
+        );
+    }
+
+    let batch_number = job.batch_number;
+    tracing::info!("Proving batch number {batch_number} from {:?}", args.input);
+    let started_at = Instant::now();
+    let proof = create_proof(&prover, batch_number as u64, prover_input)?;
+    let proving_time_secs = started_at.elapsed().as_secs_f64();
+    tracing::info!("Proved batch number {batch_number} in {proving_time_secs} seconds");
+
+    let proof_bytes = bincode::serde::encode_to_vec(&proof, bincode::config::standard())
+        .expect("failed to bincode-serialize proof");
+    let registers = final_output_registers(&proof);
+    let output_hash_matches = job
+        .expected_hash_u32s
+        .map(|expected| registers[..8] == expected);
+
+    std::fs::create_dir_all(&args.output_dir)
+        .with_context(|| format!("Failed to create {:?}", args.output_dir))?;
+    let proof_path = args.output_dir.join(format!(
+        "fri_proof_{batch_number}.{}",
+        args.format.extension()
+    ));
+    let metadata = ProofMetadata {
+        batch_number,
+        vk_hash: vk_hash.clone(),
+        app_bin_path: binary_path,
+        program_commitment: program_commitment.to_string(),
+        security_level: format!("{:?}", supported_versions.proving_security_level()),
+        prover_input_bytes: job.prover_input.len(),
+        prover_input_sha256: payload_digest(&job.prover_input),
+        proof_file: proof_path.clone(),
+        proof_sha256: payload_digest(&proof_bytes),
+        proving_time_secs,
+        final_output_registers: registers,
+        expected_hash_u32s: job.expected_hash_u32s,
+        output_hash_matches,
+    };
+    FriProofFile {
+        batch_number,
+        vk_hash: job.vk_hash,
+        proof: proof_bytes,
+    }
+    .write_to(&proof_path, Some(args.format))?;
+    let metadata_path = args
+        .output_dir
+        .join(format!("fri_proof_{batch_number}.meta.json"));
+    write_metadata(&metadata_path, &metadata)?;
+    tracing::info!("Saved proof to {proof_path:?} and its metadata to {metadata_path:?}");
+
+    anyhow::ensure!(
+        output_hash_matches != Some(false),
+        "proof for batch number {batch_number} outputs {:?}, but the job expects {:?}",
+        &registers[..8],
+        job.expected_hash_u32s.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// The job in `data`: a FRI job envelope, or a raw prover input for `batch_number`. Values
+/// given for an envelope must match it.
+fn parse_job(
+    data: &[u8],
+    batch_number: Option<u32>,
+    vk_hash: Option<String>,
+) -> anyhow::Result<FriJobInputs> {
+    // Only what doesn't look like an envelope is a raw input: a damaged or newer envelope
+    // must not be proven as one.
+    if EnvelopeFormat::detect(data).is_some() {
+        let job = FriJobInputs::from_envelope(Envelope::decode(data)?)?;
+        if let Some(batch_number) = batch_number {
+            anyhow::ensure!(
+                batch_number == job.batch_number,
+                "batch number {batch_number} does not match the job's {}",
+                job.batch_number
+            );
+        }
+        if let Some(vk_hash) = vk_hash {
+            anyhow::ensure!(
+                vk_hash == job.vk_hash,
+                "vk hash {vk_hash} does not match the job's {}",
+                job.vk_hash
+            );
+        }
+        return Ok(job);
+    }
+    // A JSON array of bytes is what `pick-fri` saved before envelopes.
+    let prover_input = if data.trim_ascii_start().starts_with(b"[") {
+        serde_json::from_slice(data).context("Failed to parse prover input as a byte array")?
+    } else {
+        data.to_vec()
+    };
+    Ok(FriJobInputs {
+        batch_number: batch_number.unwrap_or_default(),
+        vk_hash: vk_hash.unwrap_or_default(),
+        prover_input,
+        expected_hash_u32s: None,
+        last_block_timestamp: None,
+    })
+}
+
+fn write_metadata(path: &Path, metadata: &ProofMetadata) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {path:?}"))?;
+    serde_json::to_writer_pretty(file, metadata)
+        .with_context(|| format!("Failed to write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_are_parsed_from_envelopes_and_raw_inputs() {
+        let job = FriJobInputs {
+            batch_number: 7,
+            vk_hash: "0xaa".to_string(),
+            prover_input: vec![1, 0, 0, 0],
+            expected_hash_u32s: Some([1; 8]),
+            last_block_timestamp: None,
+        };
+        let envelope = job.to_envelope().unwrap().encode(EnvelopeFormat::Bin);
+        let parsed = parse_job(&envelope, Some(7), None).unwrap();
+        assert_eq!(parsed.vk_hash, "0xaa");
+        assert_eq!(parsed.expected_hash_u32s, Some([1; 8]));
+        assert!(parse_job(&envelope, Some(8), None).is_err());
+        assert!(parse_job(&envelope, None, Some("0xbb".to_string())).is_err());
+
+        let mut newer: serde_json::Value =
+            serde_json::from_slice(&job.to_envelope().unwrap().encode(EnvelopeFormat::Json))
+                .unwrap();
+        newer["envelope"] = 2.into();
+        let err = parse_job(&serde_json::to_vec(&newer).unwrap(), None, None).unwrap_err();
+        assert!(err.to_string().contains("version 2"), "{err}");
+
+        let parsed = parse_job(b"[1, 0, 0, 0]", Some(3), None).unwrap();
+        assert_eq!(parsed.batch_number, 3);
+        assert_eq!(parsed.prover_input, vec![1, 0, 0, 0]);
+        assert_eq!(parsed.vk_hash, "");
+
+        let parsed = parse_job(&[2, 0, 0, 0], None, Some("0xaa".to_string())).unwrap();
+        assert_eq!(parsed.batch_number, 0);
+        assert_eq!(parsed.prover_input, vec![2, 0, 0, 0]);
+    }
+}
@@ -7,9 +7,14 @@ use zksync_os_prover_service::{init_tracing, metrics};
 
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
-    init_tracing();
     let args = zksync_os_prover_service::Args::parse();
+    let telemetry = init_tracing(&args.telemetry)?;
+    let result = run(args).await;
+    telemetry.shutdown().await;
+    result
+}
 
+async fn run(args: zksync_os_prover_service::Args) -> anyhow::Result<()> {
     let (stop_sender, stop_receiver) = watch::channel(false);
 
     let prometheus_port = args.prometheus_port;
@@ -0,0 +1,48 @@
+//! Deriving the SNARK VK of the wrapper [`create_snark_wrapper`] builds, to reproduce and check
+//! the `vk_hash` recorded in [`SupportedProtocolVersions`].
+
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use protocol_version::SupportedProtocolVersions;
+
+use crate::{app_bin_path, create_snark_wrapper};
+
+#[derive(clap::Args, Debug)]
+pub struct GenerateVkArgs {
+    /// Path to `app.bin` bound into the SNARK VK (its `.text` sibling is derived)
+    #[arg(long)]
+    pub app_bin_path: Option<PathBuf>,
+    /// Path to the trusted setup file
+    #[arg(long)]
+    pub trusted_setup_file: String,
+    /// Path to write the VK to, as JSON
+    #[arg(long, default_value = "./snark_vk.json")]
+    pub path: PathBuf,
+}
+
+/// Derive the SNARK VK for `args.app_bin_path`, write it and print its keccak hash. Fails if
+/// the hash isn't a supported version's `vk_hash`, after writing the VK.
+pub fn generate_vk(args: GenerateVkArgs) -> anyhow::Result<()> {
+    let app_bin_path = app_bin_path(args.app_bin_path);
+    tracing::info!("Generating SNARK VK for {app_bin_path:?}");
+    let mut wrapper = create_snark_wrapper(args.trusted_setup_file, &app_bin_path)?;
+    let vk = wrapper.snark_vk()?;
+    let vk_hash = format!("{:#x}", zkos_wrapper::calculate_verification_key_hash(&vk));
+
+    let file = std::fs::File::create(&args.path)
+        .with_context(|| format!("Failed to create {:?}", args.path))?;
+    serde_json::to_writer_pretty(file, &vk)
+        .with_context(|| format!("Failed to write {:?}", args.path))?;
+    tracing::info!("Saved SNARK VK to {:?}", args.path);
+    println!("{vk_hash}");
+
+    let supported_versions = SupportedProtocolVersions::default();
+    anyhow::ensure!(
+        supported_versions.contains(&vk_hash),
+        "VK hash {vk_hash} is not a supported protocol version's (supported: {})",
+        supported_versions.vk_hashes().join(", ")
+    );
+    tracing::info!("VK hash {vk_hash} matches a supported protocol version");
+    Ok(())
+}
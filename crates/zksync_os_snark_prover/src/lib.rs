@@ -1,8 +1,8 @@
 use anyhow::Context as _;
 use protocol_version::{ProgramCommitment, SupportedProtocolVersions};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use zkos_wrapper::{
     CompressionProof, SnarkWrapper, SnarkWrapperConfig, SnarkWrapperHostCache, SnarkWrapperProof,
 };
@@ -15,15 +15,27 @@ use zksync_airbender_cli::prover_utils::{
     ProverBackend, SecurityLevel,
 };
 use zksync_airbender_execution_utils::unrolled::UnrolledProgramProof;
-use zksync_sequencer_proof_client::{ProofClient, SnarkProofInputs};
+use zksync_sequencer_proof_client::{
+    with_lease_heartbeat, Enveloped, IdleBackoff, LeasedJob, ProofClient, ProofClientError,
+    ProofSpool, ReleaseReason, SequencerPool, SnarkProofFile, SnarkProofInputs, SpooledProof,
+    TelemetryArgs, TelemetryGuard, LEASE_HEARTBEAT_INTERVAL,
+};
 
 use crate::metrics::{SnarkProofTimeStats, SnarkStage, SNARK_PROVER_METRICS};
 
+pub mod generate_vk;
 pub mod metrics;
+pub mod wrap;
+
+pub fn init_tracing(telemetry: &TelemetryArgs) -> anyhow::Result<TelemetryGuard> {
+    zksync_sequencer_proof_client::init_tracing("zksync_os_snark_prover", "info", telemetry)
+}
 
-pub fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    FmtSubscriber::builder().with_env_filter(filter).init();
+/// `app_bin_path`, or the repo's `multiblock_batch.bin` if unset, mirroring the FRI prover /
+/// prover service.
+pub fn app_bin_path(app_bin_path: Option<PathBuf>) -> PathBuf {
+    let manifest_path = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    app_bin_path.unwrap_or_else(|| Path::new(&manifest_path).join("../../multiblock_batch.bin"))
 }
 
 /// Where [`run_inner`] gets its SNARK wrapper from.
@@ -333,21 +345,135 @@ pub fn merge_fris(
     Ok(combined.proof)
 }
 
+/// Check that the FRI proofs of a job prove the app program of its protocol version, so
+/// the wrapper (whose VK binds that program) can wrap them. Versions without a recorded
+/// commitment aren't checked.
+pub fn check_program_commitments(
+    snark_proof_input: &SnarkProofInputs,
+    supported_protocol_versions: &SupportedProtocolVersions,
+) -> anyhow::Result<()> {
+    let Some(expected) =
+        supported_protocol_versions.program_commitment_for(&snark_proof_input.vk_hash)
+    else {
+        return Ok(());
+    };
+    let single = snark_proof_input.fri_proofs.len() == 1;
+    for (i, proof) in snark_proof_input.fri_proofs.iter().enumerate() {
+        let output = output_program_commitment(proof);
+        anyhow::ensure!(
+            output == expected,
+            "FRI proof {i} proves program commitment {output}, but protocol version {} \
+             proves {expected}",
+            snark_proof_input.vk_hash,
+        );
+        // A single-proof job skips the merge, so the wrapped proof's raw
+        // registers 18..=25 (not the continued chain) meet check_aux_params.
+        let carried = carried_program_commitment(proof);
+        anyhow::ensure!(
+            !single || carried == expected,
+            "the single FRI proof converged in one unified pass and carries the pre-merge \
+             chain {carried} in registers 18..=25, but the wrapper VK constrains them to \
+             {expected}; unprovable with the current VK — the job needs >=2 FRI proofs (or \
+             the proof an extra unified pass)"
+        );
+    }
+    Ok(())
+}
+
+/// Merge a job's FRI proofs, then wrap, compress and SNARKify the result with the wrapper
+/// from `wrapper_source`.
+pub fn wrap_fri_proofs(
+    snark_proof_input: SnarkProofInputs,
+    wrapper_source: &mut WrapperSource,
+    combiner: &mut CarriedChainCombiner,
+    disable_zk: bool,
+) -> anyhow::Result<SnarkWrapperProof> {
+    let mut stats = SnarkProofTimeStats::new();
+
+    // A job whose proofs fail to combine would be re-picked forever, so treat merge
+    // failures as fatal rather than skipping the job.
+    let proof = stats.measure_step(SnarkStage::MergeFri, || {
+        merge_fris(snark_proof_input, combiner)
+    })?;
+
+    // Materialize the wrapper only after the merge: the merge's GPU prover sizes its
+    // device pool to all free VRAM, so the wrapper's device-resident state must not
+    // be alive yet. In `PerJob` mode the wrapper (and any VRAM it touches) lives
+    // exactly from here until this job's proving is done; its host-side setup caches
+    // carry over from the previous job, so only the first job of the process pays the
+    // full setup derivation in the `wrapper_setup` stage.
+    let mut per_job_wrapper = None;
+    let snark_wrapper: &mut SnarkWrapper = match wrapper_source {
+        WrapperSource::Resident(wrapper) => wrapper,
+        WrapperSource::PerJob {
+            trusted_setup_file,
+            app_bin_path,
+            host_cache,
+        } => {
+            tracing::info!("Building per-job SNARK wrapper");
+            let cache = host_cache.take().map(|cache| *cache);
+            per_job_wrapper = Some(stats.measure_step(SnarkStage::WrapperSetup, || {
+                create_snark_wrapper_with_cache(trusted_setup_file.clone(), app_bin_path, cache)
+            })?);
+            per_job_wrapper.as_mut().expect("wrapper was just built")
+        }
+    };
+
+    tracing::info!("Wrapping and compressing FRI proof");
+
+    // Proving failures are fatal: silently skipping would re-pick the same job forever, and a
+    // failed attempt can leave the wrapper's cached GPU state unusable for the FRI phase of the
+    // zksync_os_prover_service service that runs FRI and SNARK on the same process.
+    let compression_proof: CompressionProof = stats
+        .measure_step(SnarkStage::FinalProof, || {
+            let risc_wrapper_proof =
+                tracing::info_span!("wrap").in_scope(|| snark_wrapper.prove_risc_wrapper(proof))?;
+            tracing::info_span!("compression")
+                .in_scope(|| snark_wrapper.prove_compression(risc_wrapper_proof))
+        })
+        .map_err(|e| anyhow::anyhow!("failed to wrap/compress FRI proof: {e:?}"))?;
+
+    tracing::info!("SNARKifying proof");
+    // note that the API is use_zk, so we invert the disable_zk flag
+    let snark_proof: SnarkWrapperProof = stats
+        .measure_step(SnarkStage::Snark, || {
+            snark_wrapper.prove_snark(compression_proof, !disable_zk)
+        })
+        .map_err(|e| anyhow::anyhow!("failed to SNARKify proof: {e:?}"))?;
+    stats.observe_full();
+    tracing::info!("Finished generating proof, time stats: {}", stats);
+
+    // The per-job wrapper is done with the GPU; retire it but keep its host-side setup
+    // caches so the next job's wrapper build is a cheap rehydration instead of a full
+    // re-derivation.
+    if let Some(wrapper) = per_job_wrapper {
+        if let WrapperSource::PerJob { host_cache, .. } = wrapper_source {
+            *host_cache = Some(Box::new(wrapper.into_host_cache()));
+        }
+    }
+
+    Ok(snark_proof)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run_linking_fri_snark(
-    clients: Vec<Box<dyn ProofClient + Send + Sync>>,
+    pool: SequencerPool,
+    concurrent_pick: bool,
+    mut idle_backoff: IdleBackoff,
     output_dir: String,
     trusted_setup_file: String,
     app_bin_path: PathBuf,
     iterations: Option<usize>,
     disable_zk: bool,
+    spool: Option<Arc<ProofSpool>>,
 ) -> anyhow::Result<()> {
     let startup_started_at = Instant::now();
 
     tracing::info!(
         "Initializing SNARK prover with {} sequencer(s):",
-        clients.len()
+        pool.clients().len()
     );
-    for client in clients.iter() {
+    for client in pool.clients().iter() {
         tracing::info!("  - {}", client.sequencer_url());
     }
 
@@ -370,19 +496,42 @@ pub async fn run_linking_fri_snark(
 
     let mut proof_count = 0;
 
-    // Cycle through clients in round-robin fashion
-    for client in clients.iter().cycle() {
-        tracing::debug!("Polling sequencer: {}", client.sequencer_url());
-
-        let proof_generated = run_inner(
-            client.as_ref(),
-            &mut wrapper_source,
-            &mut combiner,
-            output_dir.clone(),
-            disable_zk,
-            &supported_versions,
-        )
-        .await
+    // Poll sequencers as scheduled by the pool, skipping unhealthy ones
+    loop {
+        let long_polled;
+        let proof_generated = if concurrent_pick {
+            long_polled = pool.long_polls();
+            match pool.pick_any_snark_job(report_pick_error).await {
+                Some((client, snark_proof_input)) => {
+                    prove_job(
+                        client,
+                        snark_proof_input,
+                        &mut wrapper_source,
+                        &mut combiner,
+                        output_dir.clone(),
+                        disable_zk,
+                        &supported_versions,
+                        spool.as_deref(),
+                    )
+                    .await
+                }
+                None => Ok(false),
+            }
+        } else {
+            let client = pool.next_client().await;
+            tracing::debug!("Polling sequencer: {}", client.sequencer_url());
+            long_polled = client.long_poll_wait().is_some();
+            run_inner(
+                client,
+                &mut wrapper_source,
+                &mut combiner,
+                output_dir.clone(),
+                disable_zk,
+                &supported_versions,
+                spool.as_deref(),
+            )
+            .await
+        }
         .expect("Failed to run SNARK prover");
 
         if proof_generated {
@@ -396,14 +545,17 @@ pub async fn run_linking_fri_snark(
                     return Ok(());
                 }
             }
+            idle_backoff.reset();
         } else {
             // If no task was found, wait before trying again
-            tracing::info!("No pending SNARK jobs from sequencer, retrying in 5s...");
-            tokio::time::sleep(Duration::from_secs(5)).await;
+            let delay = idle_backoff.next_delay(long_polled);
+            tracing::debug!(
+                "No pending SNARK jobs from sequencer, retrying in {} ms",
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
         }
     }
-
-    Ok(())
 }
 
 pub async fn run_inner(
@@ -413,68 +565,11 @@ pub async fn run_inner(
     output_dir: String,
     disable_zk: bool,
     supported_protocol_versions: &SupportedProtocolVersions,
+    spool: Option<&ProofSpool>,
 ) -> anyhow::Result<bool> {
     tracing::debug!("Picking job from sequencer {}", client.sequencer_url());
     let snark_proof_input = match client.pick_snark_job().await {
-        Ok(Some(snark_proof_input)) => {
-            if snark_proof_input.fri_proofs.is_empty() {
-                let err_msg =
-                    "No FRI proofs were sent, issue with Prover API/Sequencer, quitting...";
-                tracing::error!(err_msg);
-                return Err(anyhow::anyhow!(err_msg));
-            }
-            if !supported_protocol_versions.contains(&snark_proof_input.vk_hash) {
-                tracing::error!(
-                    "Received unsupported protocol version with vk_hash {} for batches between [{} and {}] from sequencer {}, skipping",
-                    snark_proof_input.vk_hash,
-                    snark_proof_input.from_batch_number.0,
-                    snark_proof_input.to_batch_number.0,
-                    client.sequencer_url()
-                );
-                return Ok(false);
-            }
-            // Reject wrong-program proofs up front with a clear error. The wrapper VK now
-            // binds the app program (check_aux_params constrains registers 18..=25 to the
-            // version's commitment), so such a proof would otherwise fail deep in wrap
-            // proving as an unsatisfiable circuit, after the GPU time is already spent.
-            if let Some(expected) =
-                supported_protocol_versions.program_commitment_for(&snark_proof_input.vk_hash)
-            {
-                let single = snark_proof_input.fri_proofs.len() == 1;
-                for (i, proof) in snark_proof_input.fri_proofs.iter().enumerate() {
-                    let output = output_program_commitment(proof);
-                    if output != expected {
-                        tracing::error!(
-                            "FRI proof {i} of batches [{} to {}] from sequencer {} proves \
-                             program commitment {output}, but protocol version {} proves \
-                             {expected}; skipping",
-                            snark_proof_input.from_batch_number.0,
-                            snark_proof_input.to_batch_number.0,
-                            client.sequencer_url(),
-                            snark_proof_input.vk_hash,
-                        );
-                        return Ok(false);
-                    }
-                    // A single-proof job skips the merge, so the wrapped proof's raw
-                    // registers 18..=25 (not the continued chain) meet check_aux_params.
-                    let carried = carried_program_commitment(proof);
-                    if single && carried != expected {
-                        tracing::error!(
-                            "single-proof SNARK job for batches [{} to {}]: the FRI proof \
-                             converged in one unified pass and carries the pre-merge chain \
-                             {carried} in registers 18..=25, but the wrapper VK constrains \
-                             them to {expected}; unprovable with the current VK — the job \
-                             needs >=2 FRI proofs (or the proof an extra unified pass); \
-                             skipping",
-                            snark_proof_input.from_batch_number.0,
-                            snark_proof_input.to_batch_number.0,
-                        );
-                        return Ok(false);
-                    }
-                }
-            }
-            snark_proof_input
-        }
+        Ok(Some(snark_proof_input)) => snark_proof_input,
         Ok(None) => {
             tracing::debug!(
                 "No SNARK jobs found from sequencer {}",
@@ -483,26 +578,121 @@ pub async fn run_inner(
             return Ok(false);
         }
         Err(e) => {
-            // Check if the error is a timeout error
-            if e.downcast_ref::<reqwest::Error>()
-                .map(|err| err.is_timeout())
-                .unwrap_or(false)
-            {
-                tracing::error!(
-                    "Timeout waiting for response from sequencer {}: {e:?}",
-                    client.sequencer_url()
-                );
-                tracing::error!("Exiting prover due to timeout");
-                SNARK_PROVER_METRICS.timeout_errors.inc();
-                return Ok(false);
-            }
+            report_pick_error(client, e);
+            return Ok(false);
+        }
+    };
+    prove_job(
+        client,
+        snark_proof_input,
+        wrapper_source,
+        combiner,
+        output_dir,
+        disable_zk,
+        supported_protocol_versions,
+        spool,
+    )
+    .await
+}
+
+/// Log and count a failed pick from `client`.
+pub fn report_pick_error(client: &dyn ProofClient, e: ProofClientError) {
+    SNARK_PROVER_METRICS.client_errors[&e.kind()].inc();
+    match e {
+        ProofClientError::Timeout(_) => {
             tracing::error!(
-                "Failed to pick SNARK job from sequencer {}: {e:?}",
+                "Timeout waiting for response from sequencer {}: {e}",
                 client.sequencer_url()
             );
-            return Ok(false);
+            SNARK_PROVER_METRICS.timeout_errors.inc();
         }
+        ProofClientError::Unauthorized(_) => {
+            tracing::error!(
+                "Sequencer {} rejected the prover's credentials: {e}",
+                client.sequencer_url()
+            );
+        }
+        _ => {
+            tracing::error!(
+                "Failed to pick SNARK job from sequencer {}: {e}",
+                client.sequencer_url()
+            );
+        }
+    }
+}
+
+/// SNARK a job picked from `client` and submit the proof back to it. Jobs the prover can't
+/// prove are released; returns whether a proof was generated.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "snark_job",
+    skip_all,
+    fields(
+        from_batch_number = snark_proof_input.from_batch_number.0,
+        to_batch_number = snark_proof_input.to_batch_number.0,
+        vk_hash = %snark_proof_input.vk_hash,
+        sequencer = %client.sequencer_url(),
+    )
+)]
+pub async fn prove_job(
+    client: &dyn ProofClient,
+    snark_proof_input: SnarkProofInputs,
+    wrapper_source: &mut WrapperSource,
+    combiner: &mut CarriedChainCombiner,
+    output_dir: String,
+    disable_zk: bool,
+    supported_protocol_versions: &SupportedProtocolVersions,
+    spool: Option<&ProofSpool>,
+) -> anyhow::Result<bool> {
+    let leased_job = LeasedJob::Snark {
+        from_batch_number: snark_proof_input.from_batch_number,
+        to_batch_number: snark_proof_input.to_batch_number,
     };
+    if snark_proof_input.fri_proofs.is_empty() {
+        let err_msg = "No FRI proofs were sent, issue with Prover API/Sequencer, quitting...";
+        tracing::error!(err_msg);
+        leased_job
+            .release(
+                client,
+                ReleaseReason::MalformedInput,
+                "job carries no FRI proofs".to_string(),
+            )
+            .await;
+        return Err(anyhow::anyhow!(err_msg));
+    }
+    if !supported_protocol_versions.contains(&snark_proof_input.vk_hash) {
+        tracing::error!(
+            "Received unsupported protocol version with vk_hash {} for batches between [{} and {}] from sequencer {}, skipping",
+            snark_proof_input.vk_hash,
+            snark_proof_input.from_batch_number.0,
+            snark_proof_input.to_batch_number.0,
+            client.sequencer_url()
+        );
+        leased_job
+            .release(
+                client,
+                ReleaseReason::UnsupportedVersion,
+                format!("unsupported vk_hash {}", snark_proof_input.vk_hash),
+            )
+            .await;
+        return Ok(false);
+    }
+    // Reject wrong-program proofs up front with a clear error. The wrapper VK now
+    // binds the app program (check_aux_params constrains registers 18..=25 to the
+    // version's commitment), so such a proof would otherwise fail deep in wrap
+    // proving as an unsatisfiable circuit, after the GPU time is already spent.
+    if let Err(err) = check_program_commitments(&snark_proof_input, supported_protocol_versions) {
+        tracing::error!(
+            "SNARK job for batches [{} to {}] from sequencer {}: {err:#}; skipping",
+            snark_proof_input.from_batch_number.0,
+            snark_proof_input.to_batch_number.0,
+            client.sequencer_url(),
+        );
+        leased_job
+            .release(client, ReleaseReason::ProgramMismatch, format!("{err:#}"))
+            .await;
+        return Ok(false);
+    }
     let start_batch = snark_proof_input.from_batch_number;
     let end_batch = snark_proof_input.to_batch_number;
     let vk_hash = snark_proof_input.vk_hash.clone();
@@ -515,80 +705,63 @@ pub async fn run_inner(
         end_batch,
     );
 
-    let mut stats = SnarkProofTimeStats::new();
-
-    // A job whose proofs fail to combine would be re-picked forever, so treat merge
-    // failures as fatal rather than skipping the job.
-    let proof = stats.measure_step(SnarkStage::MergeFri, || {
-        merge_fris(snark_proof_input, combiner)
-    })?;
-
-    // Materialize the wrapper only after the merge: the merge's GPU prover sizes its
-    // device pool to all free VRAM, so the wrapper's device-resident state must not
-    // be alive yet. In `PerJob` mode the wrapper (and any VRAM it touches) lives
-    // exactly from here until this job's proving is done; its host-side setup caches
-    // carry over from the previous job, so only the first job of the process pays the
-    // full setup derivation in the `wrapper_setup` stage.
-    let mut per_job_wrapper = None;
-    let snark_wrapper: &mut SnarkWrapper = match wrapper_source {
-        WrapperSource::Resident(wrapper) => wrapper,
-        WrapperSource::PerJob {
-            trusted_setup_file,
-            app_bin_path,
-            host_cache,
-        } => {
-            tracing::info!("Building per-job SNARK wrapper");
-            let cache = host_cache.take().map(|cache| *cache);
-            per_job_wrapper = Some(stats.measure_step(SnarkStage::WrapperSetup, || {
-                create_snark_wrapper_with_cache(trusted_setup_file.clone(), app_bin_path, cache)
-            })?);
-            per_job_wrapper.as_mut().expect("wrapper was just built")
+    // Keep the job's lease alive for the whole proving; merging, wrapping and SNARKifying
+    // together can outlast the sequencer's lease timeout.
+    let proving_result = with_lease_heartbeat(client, leased_job, LEASE_HEARTBEAT_INTERVAL, || {
+        wrap_fri_proofs(snark_proof_input, wrapper_source, combiner, disable_zk)
+    });
+    let snark_proof = match proving_result {
+        Ok(snark_proof) => snark_proof,
+        Err(err) => {
+            leased_job
+                .release(client, ReleaseReason::ProvingFailed, format!("{err:#}"))
+                .await;
+            return Err(err);
         }
     };
 
-    tracing::info!("Wrapping and compressing FRI proof");
-
-    // Proving failures are fatal: silently skipping would re-pick the same job forever, and a
-    // failed attempt can leave the wrapper's cached GPU state unusable for the FRI phase of the
-    // zksync_os_prover_service service that runs FRI and SNARK on the same process.
-    let compression_proof: CompressionProof = stats
-        .measure_step(SnarkStage::FinalProof, || {
-            let risc_wrapper_proof = snark_wrapper.prove_risc_wrapper(proof)?;
-            snark_wrapper.prove_compression(risc_wrapper_proof)
-        })
-        .map_err(|e| anyhow::anyhow!("failed to wrap/compress FRI proof: {e:?}"))?;
-
-    tracing::info!("SNARKifying proof");
-    // note that the API is use_zk, so we invert the disable_zk flag
-    let snark_proof: SnarkWrapperProof = stats
-        .measure_step(SnarkStage::Snark, || {
-            snark_wrapper.prove_snark(compression_proof, !disable_zk)
-        })
-        .map_err(|e| anyhow::anyhow!("failed to SNARKify proof: {e:?}"))?;
-    stats.observe_full();
-    tracing::info!("Finished generating proof, time stats: {}", stats);
-
-    // The per-job wrapper is done with the GPU; retire it but keep its host-side setup
-    // caches so the next job's wrapper build is a cheap rehydration instead of a full
-    // re-derivation.
-    if let Some(wrapper) = per_job_wrapper {
-        if let WrapperSource::PerJob { host_cache, .. } = wrapper_source {
-            *host_cache = Some(Box::new(wrapper.into_host_cache()));
-        }
-    }
-
-    // Persist the proof next to the other artifacts, mirroring the old flow (best effort).
+    // Persist the proof next to the other artifacts, as an envelope the proof client CLI can
+    // submit (best effort).
     let snark_proof_path = Path::new(&output_dir).join("snark_proof.json");
-    if let Some(path) = snark_proof_path.to_str() {
-        if let Err(e) = zkos_wrapper::serialize_to_file(&snark_proof, path) {
-            tracing::warn!("failed to persist SNARK proof to {path}: {e:?}");
-        }
+    let proof_file = SnarkProofFile {
+        from_batch_number: start_batch,
+        to_batch_number: end_batch,
+        vk_hash: vk_hash.clone(),
+        proof: snark_proof.clone(),
+    };
+    if let Err(e) = proof_file.write_to(&snark_proof_path, None) {
+        tracing::warn!("failed to persist SNARK proof to {snark_proof_path:?}: {e:#}");
     }
 
-    match client
+    // Persist the proof before submitting, so a failed submission doesn't lose it.
+    let spool_ticket = match spool {
+        Some(spool) => spool
+            .put(
+                client.sequencer_url(),
+                SpooledProof::Snark {
+                    from_batch_number: start_batch,
+                    to_batch_number: end_batch,
+                    vk_hash: vk_hash.clone(),
+                    proof: snark_proof.clone(),
+                },
+            )
+            .await
+            .inspect_err(|e| {
+                tracing::error!(
+                    "Failed to spool SNARK proof for batches {start_batch} to {end_batch}: {e:#}"
+                )
+            })
+            .ok(),
+        None => None,
+    };
+    let result = client
         .submit_snark_proof(start_batch, end_batch, vk_hash.clone(), snark_proof)
-        .await
-    {
+        .await;
+    if let (Some(spool), Some(ticket)) = (spool, spool_ticket) {
+        spool.settle(ticket, &result).await;
+    }
+
+    match result {
         Ok(()) => {
             tracing::info!(
                 "Successfully submitted SNARK proof for batches {} to {} with vk hash {} to sequencer {}",
@@ -605,28 +778,37 @@ pub async fn run_inner(
             Ok(true)
         }
         Err(e) => {
-            // Check if the error is a timeout error
-            if e.downcast_ref::<reqwest::Error>()
-                .map(|err| err.is_timeout())
-                .unwrap_or(false)
-            {
-                tracing::error!(
-                    "Timeout submitting SNARK proof with vk hash {} for batches {} to {} to sequencer {}: {e:?}",
-                    vk_hash,
-                    start_batch,
-                    end_batch,
-                    client.sequencer_url()
-                );
-                tracing::error!("Exiting prover due to timeout");
-                SNARK_PROVER_METRICS.timeout_errors.inc();
-            } else {
-                tracing::error!(
-                    "Failed to submit SNARK job with vk hash {}, batches {} to {} to sequencer {} due to {e:?}, skipping",
-                    vk_hash,
-                    start_batch,
-                    end_batch,
-                    client.sequencer_url(),
-                );
+            SNARK_PROVER_METRICS.client_errors[&e.kind()].inc();
+            match e {
+                ProofClientError::Timeout(_) => {
+                    tracing::error!(
+                        "Timeout submitting SNARK proof with vk hash {} for batches {} to {} to sequencer {}: {e}",
+                        vk_hash,
+                        start_batch,
+                        end_batch,
+                        client.sequencer_url()
+                    );
+                    SNARK_PROVER_METRICS.timeout_errors.inc();
+                }
+                ProofClientError::AlreadyProven | ProofClientError::LeaseExpired => {
+                    tracing::warn!(
+                        "Sequencer {} did not accept SNARK proof with vk hash {} for batches {} to {}: {e}; \
+                         the job was proven or reassigned elsewhere, dropping the proof",
+                        client.sequencer_url(),
+                        vk_hash,
+                        start_batch,
+                        end_batch,
+                    );
+                }
+                _ => {
+                    tracing::error!(
+                        "Failed to submit SNARK job with vk hash {}, batches {} to {} to sequencer {} due to {e}, skipping",
+                        vk_hash,
+                        start_batch,
+                        end_batch,
+                        client.sequencer_url(),
+                    );
+                }
             }
             // Return false so caller doesn't increment proof counter
             Ok(false)
@@ -2,8 +2,9 @@ use core::fmt;
 use std::{collections::HashMap, net::Ipv4Addr, time::Duration};
 
 use tokio::{sync::watch, time::Instant};
-use vise::{Counter, Gauge, Histogram, Metrics, MetricsCollection};
+use vise::{Counter, Family, Gauge, Histogram, Metrics, MetricsCollection};
 use vise_exporter::MetricsExporter;
+use zksync_sequencer_proof_client::ProofClientErrorKind;
 
 pub async fn start_metrics_exporter(
     port: u16,
@@ -61,6 +62,8 @@ pub struct SnarkProverMetrics {
     pub latest_proven_batch: Gauge,
     /// Number of timeout errors when communicating with sequencer
     pub timeout_errors: Counter,
+    /// Number of failed sequencer calls, by error kind
+    pub client_errors: Family<ProofClientErrorKind, Counter>,
 }
 
 #[vise::register]
@@ -151,6 +154,7 @@ impl SnarkProofTimeStats {
     where
         F: FnOnce() -> T,
     {
+        let _span = tracing::info_span!("snark_stage", %stage).entered();
         let start = Instant::now();
         let result = step();
         self.observe_step(stage, start.elapsed());
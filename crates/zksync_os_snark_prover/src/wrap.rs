@@ -0,0 +1,192 @@
+//! Wrapping FRI proofs from files into a SNARK, without a sequencer: for regenerating or
+//! debugging the SNARK of a specific batch range.
+
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use protocol_version::SupportedProtocolVersions;
+use zksync_sequencer_proof_client::{
+    payload_digest, snark_proof_bytes, Envelope, EnvelopeFormat, EnvelopeHeader, Enveloped,
+    FriProofFile, L2BatchNumber, SnarkProofFile, SnarkProofInputs,
+};
+
+use crate::{
+    app_bin_path, check_program_commitments, create_combiner, wrap_fri_proofs, WrapperSource,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct WrapArgs {
+    /// FRI proof envelopes of consecutive batches, in batch order (e.g. written by
+    /// `zksync_os_fri_prover --path` or `prove`), or a single SNARK job envelope (e.g. saved
+    /// by `zksync_sequencer_proof_client pick-snark`)
+    #[arg(short, long = "fri-proof", value_delimiter = ',', num_args = 1.., required = true)]
+    pub fri_proofs: Vec<PathBuf>,
+    /// First batch of the range [default: the first proof's]; checked against the proofs
+    #[arg(long)]
+    pub from_batch_number: Option<u32>,
+    /// Last batch of the range [default: the last proof's]; checked against the proofs
+    #[arg(long)]
+    pub to_batch_number: Option<u32>,
+    /// vk hash the SNARK is generated for [default: the proofs']; checked against the proofs
+    #[arg(long)]
+    pub vk_hash: Option<String>,
+    /// Path to `app.bin` bound into the SNARK VK (its `.text` sibling is derived)
+    #[arg(long)]
+    pub app_bin_path: Option<PathBuf>,
+    /// Path to the trusted setup file
+    #[arg(long)]
+    pub trusted_setup_file: String,
+    /// Directory to write `snark_proof_<from>_<to>.<ext>` and `snark_proof_<from>_<to>.raw`
+    /// to
+    #[arg(long, default_value = ".")]
+    pub output_dir: PathBuf,
+    /// Format of the proof envelope
+    #[arg(long, value_enum, default_value_t = EnvelopeFormat::Json)]
+    pub format: EnvelopeFormat,
+    /// Disable ZK for the SNARK proof
+    #[arg(long, default_value_t = false)]
+    pub disable_zk: bool,
+}
+
+/// Merge, wrap and SNARKify the FRI proofs of `args.fri_proofs` with the same checks as a
+/// picked job, and write the SNARK proof: as an envelope the proof client CLI can submit, and
+/// serialized as submitted to the sequencer (big-endian 32-byte words, before base64).
+pub fn wrap_locally(args: WrapArgs) -> anyhow::Result<()> {
+    let job = load_job(
+        &args.fri_proofs,
+        args.from_batch_number,
+        args.to_batch_number,
+        args.vk_hash,
+    )?;
+    let supported_versions = SupportedProtocolVersions::default();
+    anyhow::ensure!(
+        supported_versions.contains(&job.vk_hash),
+        "unsupported protocol version with vk_hash {}",
+        job.vk_hash
+    );
+    check_program_commitments(&job, &supported_versions)?;
+
+    let from_batch_number = job.from_batch_number;
+    let to_batch_number = job.to_batch_number;
+    let vk_hash = job.vk_hash.clone();
+    tracing::info!(
+        "Wrapping {} FRI proofs for batches {from_batch_number} to {to_batch_number} with vk \
+         hash {vk_hash}",
+        job.fri_proofs.len()
+    );
+
+    // A per-job wrapper is only built once the proofs are merged, leaving the merge the
+    // whole GPU.
+    let mut wrapper_source = WrapperSource::PerJob {
+        trusted_setup_file: args.trusted_setup_file,
+        app_bin_path: app_bin_path(args.app_bin_path),
+        host_cache: None,
+    };
+    let mut combiner = create_combiner()?;
+    let proof = wrap_fri_proofs(job, &mut wrapper_source, &mut combiner, args.disable_zk)?;
+
+    std::fs::create_dir_all(&args.output_dir)
+        .with_context(|| format!("Failed to create {:?}", args.output_dir))?;
+    let stem = format!("snark_proof_{from_batch_number}_{to_batch_number}");
+    let proof_bytes = snark_proof_bytes(&proof);
+    let proof_path = args
+        .output_dir
+        .join(format!("{stem}.{}", args.format.extension()));
+    SnarkProofFile {
+        from_batch_number,
+        to_batch_number,
+        vk_hash,
+        proof,
+    }
+    .write_to(&proof_path, Some(args.format))?;
+    let raw_path = args.output_dir.join(format!("{stem}.raw"));
+    std::fs::write(&raw_path, &proof_bytes)
+        .with_context(|| format!("Failed to write {raw_path:?}"))?;
+    tracing::info!(
+        "Saved SNARK proof to {proof_path:?} and its serialized form ({} bytes, sha256 {}) to \
+         {raw_path:?}",
+        proof_bytes.len(),
+        payload_digest(&proof_bytes)
+    );
+    Ok(())
+}
+
+/// The SNARK job made of the proofs at `paths`: a SNARK job envelope, or FRI proof envelopes
+/// of the consecutive batches `from_batch_number..=to_batch_number`. Values given must match
+/// the proofs.
+fn load_job(
+    paths: &[PathBuf],
+    from_batch_number: Option<u32>,
+    to_batch_number: Option<u32>,
+    vk_hash: Option<String>,
+) -> anyhow::Result<SnarkProofInputs> {
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let envelope = Envelope::read(path)?;
+        if let EnvelopeHeader::SnarkJob { .. } = envelope.header {
+            anyhow::ensure!(
+                paths.len() == 1,
+                "{path:?} is a SNARK job, which can't be combined with other files"
+            );
+            let job = SnarkProofInputs::from_envelope(envelope)
+                .with_context(|| format!("Failed to load a SNARK job from {path:?}"))?;
+            check_matches(
+                "from batch number",
+                from_batch_number,
+                job.from_batch_number.0,
+            )?;
+            check_matches("to batch number", to_batch_number, job.to_batch_number.0)?;
+            check_matches("vk hash", vk_hash, job.vk_hash.clone())?;
+            return Ok(job);
+        }
+        let file = FriProofFile::from_envelope(envelope)
+            .with_context(|| format!("Failed to load a FRI proof from {path:?}"))?;
+        files.push((path, file));
+    }
+
+    let (Some((_, first)), Some((_, last))) = (files.first(), files.last()) else {
+        anyhow::bail!("no FRI proofs given");
+    };
+    let from_batch_number = from_batch_number.unwrap_or(first.batch_number);
+    let to_batch_number = to_batch_number.unwrap_or(last.batch_number);
+    let vk_hash = vk_hash.unwrap_or_else(|| first.vk_hash.clone());
+    anyhow::ensure!(
+        to_batch_number.checked_sub(from_batch_number) == Some(files.len() as u32 - 1),
+        "{} FRI proofs given for batches {from_batch_number} to {to_batch_number}",
+        files.len()
+    );
+
+    let mut fri_proofs = Vec::with_capacity(files.len());
+    for (batch_number, (path, file)) in (from_batch_number..).zip(&files) {
+        anyhow::ensure!(
+            file.batch_number == batch_number,
+            "{path:?} proves batch {}, expected batch {batch_number}",
+            file.batch_number
+        );
+        anyhow::ensure!(
+            file.vk_hash == vk_hash,
+            "{path:?} is for vk hash {}, expected {vk_hash}",
+            file.vk_hash
+        );
+        fri_proofs.push(file.decode_proof()?);
+    }
+    Ok(SnarkProofInputs {
+        from_batch_number: L2BatchNumber(from_batch_number),
+        to_batch_number: L2BatchNumber(to_batch_number),
+        vk_hash,
+        fri_proofs,
+    })
+}
+
+fn check_matches<T: PartialEq + std::fmt::Display>(
+    what: &str,
+    given: Option<T>,
+    in_file: T,
+) -> anyhow::Result<()> {
+    match given {
+        Some(given) if given != in_file => {
+            anyhow::bail!("The {what} {given} does not match the proofs' {in_file}")
+        }
+        _ => Ok(()),
+    }
+}